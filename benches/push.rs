@@ -0,0 +1,63 @@
+//! 1 MiB PushObject: owned `Frame` (payload cloned, then copied into the
+//! encoded frame) versus `send_frame_vectored` on the borrowed payload.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use quarxnet::error::NetResult;
+use quarxnet::frame::{FrameHeader, FrameKind};
+use quarxnet::protocol::{
+    encode_frame_into, encode_push_object, make_frame, send_frame_vectored, FrameOptions,
+    Transport,
+};
+use quarxtor_core::net_core::PushObjectPayload;
+
+/// Discards everything, so only the encoding path is measured.
+struct Sink(usize);
+
+impl Transport for Sink {
+    fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        self.0 += black_box(data).len();
+        Ok(())
+    }
+
+    fn send_vectored(&mut self, parts: &[&[u8]]) -> NetResult<()> {
+        for p in parts {
+            self.0 += black_box(p).len();
+        }
+        Ok(())
+    }
+
+    fn recv_exact(&mut self, _len: usize) -> NetResult<Vec<u8>> {
+        unreachable!()
+    }
+}
+
+fn push_1mib(c: &mut Criterion) {
+    let obj = PushObjectPayload { raw: vec![0xA5; 1024 * 1024] };
+    let mut group = c.benchmark_group("push_object_1mib");
+    group.throughput(Throughput::Bytes(obj.raw.len() as u64));
+
+    for checksum in [false, true] {
+        let opts = FrameOptions { checksum, ..FrameOptions::default() };
+
+        group.bench_function(BenchmarkId::new("owned", checksum), |b| {
+            let mut sink = Sink(0);
+            b.iter(|| {
+                let frame = make_frame(FrameKind::PushObject, encode_push_object(&obj));
+                let mut wire = Vec::new();
+                encode_frame_into(&frame, &opts, &mut wire).unwrap();
+                sink.send(&wire).unwrap();
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("vectored", checksum), |b| {
+            let mut sink = Sink(0);
+            let header = FrameHeader::new(FrameKind::PushObject, 0);
+            b.iter(|| send_frame_vectored(&mut sink, &header, &obj.raw, &opts).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, push_1mib);
+criterion_main!(benches);
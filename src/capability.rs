@@ -1 +1,163 @@
-// TODO: QuarXNet capability definitions (frames, capabilities)
+//! Optional protocol features a node can advertise in a `Caps` frame.
+//!
+//! A [`CapabilitySet`] is a 64-bit mask of well-known [`Capability`] bits
+//! plus free-form named extensions for experimental or application-level
+//! features. Both halves are intersected by [`negotiate`]; a feature may be
+//! used on a connection only if it is present in the negotiated set.
+
+use std::collections::BTreeSet;
+
+use crate::error::{NetError, NetResult};
+use crate::frame::FrameKind;
+use crate::protocol::{frame_kind_byte, FrameLimits};
+
+/// A well-known capability, identified by its bit index in the wire mask.
+///
+/// Bits are assigned as optional features land and are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Capability(u8);
+
+impl Capability {
+    /// Frames may carry a CRC32C trailer (`protocol::FLAG_CHECKSUM`).
+    pub const CRC32C: Capability = Capability(0);
+    /// PushBlocks carries a `protocol::BlockBatch` (ids, lengths, hashes)
+    /// instead of an opaque blob.
+    pub const BLOCK_BATCH: Capability = Capability(1);
+    /// Objects may be sent as chunked PushObject streams (`crate::stream`).
+    pub const OBJECT_STREAM: Capability = Capability(2);
+    /// GetBlocks carries `protocol::GetBlockRanges` (ids, ranges and a byte
+    /// budget) instead of a flat id list.
+    pub const BLOCK_RANGES: Capability = Capability(3);
+    /// Frame payloads may be LZ4-compressed (`crate::compress`).
+    pub const LZ4: Capability = Capability(4);
+    /// Frame payloads may be zstd-compressed (`crate::compress`).
+    pub const ZSTD: Capability = Capability(5);
+
+    /// Capability for bit `bit` (0..64).
+    pub const fn from_bit(bit: u8) -> Self {
+        assert!(bit < 64, "capability bit out of range");
+        Capability(bit)
+    }
+
+    pub const fn bit(self) -> u8 {
+        self.0
+    }
+
+    const fn mask(self) -> u64 {
+        1u64 << self.0
+    }
+}
+
+/// Set of capabilities advertised by, or negotiated with, a peer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilitySet {
+    bits: u64,
+    extensions: BTreeSet<String>,
+    /// Encoded size of `extensions`: u16 length plus the name, per name.
+    names_len: usize,
+}
+
+impl CapabilitySet {
+    /// Largest Caps payload a set may encode to: the Caps entry of
+    /// [`FrameLimits::default`], so every set can be sent.
+    pub const MAX_ENCODED_LEN: usize = FrameLimits::DEFAULT_CAPS_MAX as usize;
+
+    /// u64 mask and u16 extension count.
+    const FIXED_LEN: usize = 10;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a set directly from the wire mask and extension names.
+    /// Fails like [`CapabilitySet::insert_extension`].
+    pub fn from_parts<I, S>(bits: u64, extensions: I) -> NetResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut set = CapabilitySet { bits, ..Self::default() };
+        for name in extensions {
+            set.insert_extension(name)?;
+        }
+        Ok(set)
+    }
+
+    pub fn with(mut self, cap: Capability) -> Self {
+        self.insert(cap);
+        self
+    }
+
+    pub fn with_extension<S: Into<String>>(mut self, name: S) -> NetResult<Self> {
+        self.insert_extension(name)?;
+        Ok(self)
+    }
+
+    pub fn insert(&mut self, cap: Capability) {
+        self.bits |= cap.mask();
+    }
+
+    pub fn remove(&mut self, cap: Capability) {
+        self.bits &= !cap.mask();
+    }
+
+    pub fn contains(&self, cap: Capability) -> bool {
+        self.bits & cap.mask() != 0
+    }
+
+    /// Fails with [`NetError::FrameTooLarge`] if the set would no longer
+    /// fit in [`CapabilitySet::MAX_ENCODED_LEN`] bytes.
+    pub fn insert_extension<S: Into<String>>(&mut self, name: S) -> NetResult<()> {
+        let name = name.into();
+        if self.extensions.contains(&name) {
+            return Ok(());
+        }
+        let length = self.encoded_len() + 2 + name.len();
+        if length > Self::MAX_ENCODED_LEN {
+            return Err(NetError::FrameTooLarge {
+                kind: frame_kind_byte(&FrameKind::Caps),
+                length: length.min(u32::MAX as usize) as u32,
+                max: Self::MAX_ENCODED_LEN as u32,
+            });
+        }
+        self.names_len += 2 + name.len();
+        self.extensions.insert(name);
+        Ok(())
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    /// Raw mask of well-known bits, as sent on the wire.
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// Extension names in wire (lexicographic) order.
+    pub fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.extensions.iter().map(String::as_str)
+    }
+
+    /// Size of the Caps payload this set encodes to.
+    pub fn encoded_len(&self) -> usize {
+        Self::FIXED_LEN + self.names_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0 && self.extensions.is_empty()
+    }
+
+    pub fn intersection(&self, other: &CapabilitySet) -> CapabilitySet {
+        let extensions: BTreeSet<String> =
+            self.extensions.intersection(&other.extensions).cloned().collect();
+        let names_len = extensions.iter().map(|n| 2 + n.len()).sum();
+        CapabilitySet { bits: self.bits & other.bits, extensions, names_len }
+    }
+}
+
+/// Capabilities both peers may use: the intersection of what each side
+/// advertised. Unknown bits from a newer peer simply drop out.
+pub fn negotiate(local: &CapabilitySet, remote: &CapabilitySet) -> CapabilitySet {
+    local.intersection(remote)
+}
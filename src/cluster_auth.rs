@@ -0,0 +1,134 @@
+//! Shared-secret challenge/response after Hello (feature `cluster-auth`),
+//! a lighter alternative to certificates for closed clusters.
+//!
+//! Every node of a cluster is configured with the same [`ClusterSecret`].
+//! Right after Hello both sides run the same exchange:
+//!
+//! 1. send an Auth frame ([`AuthMethod::ClusterChallenge`]) with a fresh
+//!    32-byte random nonce;
+//! 2. answer the peer's challenge ([`AuthMethod::ClusterResponse`]) with
+//!    `HMAC-SHA256(secret, context | prover node | verifier node |
+//!    verifier nonce | prover nonce)`;
+//! 3. check the peer's answer against our own nonce.
+//!
+//! An answer is only valid for the nonce pair of one connection, so it
+//! cannot be replayed, and it names the direction, so it cannot be
+//! reflected back to its sender. A peer claiming our own node id is refused
+//! for the same reason.
+
+use hmac::{Hmac, Mac};
+use quarxtor_core::net_core::HelloPayload;
+use sha2::Sha256;
+
+use crate::error::{ErrorCode, NetError, NetResult};
+use crate::frame::{Frame, FrameKind};
+use crate::protocol::{
+    check_remote_error, decode_auth, encode_auth, handshake, make_frame, random_bytes, recv_frame,
+    refuse, send_frame, AuthMethod, AuthPayload, Session, Transport,
+};
+
+const MAC_CONTEXT: &[u8] = b"quarxnet cluster auth v1";
+const NONCE_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Key shared by all nodes of a cluster.
+#[derive(Clone)]
+pub struct ClusterSecret {
+    key: Vec<u8>,
+}
+
+impl ClusterSecret {
+    /// Fails with [`NetError::Config`] on an empty secret: that is not
+    /// something to authenticate with.
+    pub fn new<K: Into<Vec<u8>>>(key: K) -> NetResult<Self> {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(NetError::Config("cluster secret must not be empty".into()));
+        }
+        Ok(ClusterSecret { key })
+    }
+
+    fn mac(&self, prover: u64, verifier: u64, verifier_nonce: &[u8], prover_nonce: &[u8]) -> HmacSha256 {
+        // HMAC принимает ключ любой длины
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("hmac accepts any key length");
+        mac.update(MAC_CONTEXT);
+        mac.update(&prover.to_be_bytes());
+        mac.update(&verifier.to_be_bytes());
+        mac.update(verifier_nonce);
+        mac.update(prover_nonce);
+        mac
+    }
+}
+
+impl std::fmt::Debug for ClusterSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterSecret").finish_non_exhaustive()
+    }
+}
+
+/// [`handshake`] followed by [`authenticate`].
+pub fn cluster_handshake<T: Transport>(
+    t: &mut T,
+    local: &HelloPayload,
+    secret: &ClusterSecret,
+    notify_peer: bool,
+) -> NetResult<Session> {
+    let session = handshake(t, local, notify_peer)?;
+    authenticate(t, secret, local.node, session.peer_node, notify_peer)?;
+    Ok(session)
+}
+
+/// Runs the challenge/response exchange with the peer `peer_node` (as
+/// announced in its Hello).
+///
+/// Fails with [`NetError::ClusterAuthFailed`] if the peer's answer does not
+/// verify or the exchange is malformed; with `notify_peer` an Error frame
+/// (`Unauthorized`) is sent first. A peer that refused us is reported as
+/// [`NetError::Remote`]. Either way the caller closes the connection.
+pub fn authenticate<T: Transport>(
+    t: &mut T,
+    secret: &ClusterSecret,
+    local_node: u64,
+    peer_node: u64,
+    notify_peer: bool,
+) -> NetResult<()> {
+    let nonce: [u8; NONCE_LEN] = random_bytes()?;
+    let challenge = AuthPayload { method: AuthMethod::ClusterChallenge, data: nonce.to_vec() };
+    send_frame(t, &make_frame(FrameKind::Auth, encode_auth(&challenge)))?;
+
+    let (frame, peer_nonce) = recv_auth(t, AuthMethod::ClusterChallenge)?;
+    if peer_node == local_node || peer_nonce.len() != NONCE_LEN {
+        return Err(reject(t, &frame, peer_node, notify_peer));
+    }
+
+    let answer = secret.mac(local_node, peer_node, &peer_nonce, &nonce).finalize().into_bytes();
+    let response = AuthPayload { method: AuthMethod::ClusterResponse, data: answer.to_vec() };
+    send_frame(t, &make_frame(FrameKind::Auth, encode_auth(&response)))?;
+
+    let (frame, peer_answer) = recv_auth(t, AuthMethod::ClusterResponse)?;
+    // сравнение за постоянное время
+    let verified = secret.mac(peer_node, local_node, &nonce, &peer_nonce).verify_slice(&peer_answer);
+    if verified.is_err() {
+        return Err(reject(t, &frame, peer_node, notify_peer));
+    }
+    Ok(())
+}
+
+/// Receives the peer's next Auth frame; an Auth frame of another method
+/// comes back with empty data, which never verifies.
+fn recv_auth<T: Transport>(t: &mut T, method: AuthMethod) -> NetResult<(Frame, Vec<u8>)> {
+    let frame = check_remote_error(recv_frame(t)?)?;
+    if frame.header.kind != FrameKind::Auth {
+        return Err(NetError::InvalidFrame);
+    }
+    let p = decode_auth(&frame.payload)?;
+    let data = if p.method == method { p.data } else { Vec::new() };
+    Ok((frame, data))
+}
+
+fn reject<T: Transport>(t: &mut T, frame: &Frame, node: u64, notify_peer: bool) -> NetError {
+    let msg = format!("node {node} failed cluster authentication");
+    let err = NetError::ClusterAuthFailed { node };
+    refuse(t, frame, ErrorCode::Unauthorized, &msg, err, notify_peer)
+}
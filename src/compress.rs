@@ -0,0 +1,144 @@
+//! Per-frame payload compression (features `lz4`, `zstd`).
+//!
+//! A sender compresses a payload only if compression was negotiated
+//! (`FrameOptions::compression`), the payload is at least
+//! `FrameOptions::compress_threshold` bytes long and the result is actually
+//! smaller. Such a frame carries `protocol::FLAG_COMPRESSED`, and its
+//! payload on the wire is:
+//!
+//! - u8 codec (1 = lz4 block, 2 = zstd);
+//! - u32 big-endian length of the original payload;
+//! - the compressed bytes.
+//!
+//! Receivers undo it transparently. The announced original length must fit
+//! the frame kind's `FrameLimits` entry, so decompression never produces
+//! more than a plain frame of that kind could carry.
+
+use crate::capability::{Capability, CapabilitySet};
+use crate::error::{NetError, NetResult};
+
+/// Codec byte plus original length.
+const PREFIX_LEN: usize = 5;
+
+/// Compression codec of a frame payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// LZ4 block format: fast, moderate ratio.
+    Lz4,
+    /// Zstandard at its default level: better ratio, more CPU.
+    Zstd,
+}
+
+impl Compression {
+    fn to_u8(self) -> u8 {
+        match self {
+            Compression::Lz4  => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_u8(c: u8) -> Option<Self> {
+        match c {
+            1 => Some(Compression::Lz4),
+            2 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Capability a node advertises when it can receive this codec.
+    pub fn capability(self) -> Capability {
+        match self {
+            Compression::Lz4  => Capability::LZ4,
+            Compression::Zstd => Capability::ZSTD,
+        }
+    }
+
+    /// Codec is compiled into this build.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Compression::Lz4  => cfg!(feature = "lz4"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Capabilities of every codec this build supports, to add to the
+    /// local Caps.
+    pub fn advertise(caps: CapabilitySet) -> CapabilitySet {
+        [Compression::Zstd, Compression::Lz4]
+            .into_iter()
+            .filter(|c| c.is_supported())
+            .fold(caps, |caps, c| caps.with(c.capability()))
+    }
+
+    /// Codec to send with on a connection with the `negotiated` set:
+    /// zstd if possible, then lz4.
+    pub fn preferred(negotiated: &CapabilitySet) -> Option<Self> {
+        [Compression::Zstd, Compression::Lz4]
+            .into_iter()
+            .find(|c| c.is_supported() && negotiated.contains(c.capability()))
+    }
+}
+
+/// Compressed wire payload for `payload`, or `None` if compressing does
+/// not make it smaller.
+pub(crate) fn compress(codec: Compression, payload: &[u8]) -> Option<Vec<u8>> {
+    let data = compress_raw(codec, payload)?;
+    if PREFIX_LEN + data.len() >= payload.len() {
+        return None;
+    }
+    let mut out = Vec::with_capacity(PREFIX_LEN + data.len());
+    out.push(codec.to_u8());
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(&data);
+    Some(out)
+}
+
+/// Restores the original payload of a frame of kind `kind`, refusing to
+/// produce more than `max` bytes.
+pub(crate) fn decompress(payload: &[u8], kind: u8, max: u32) -> NetResult<Vec<u8>> {
+    if payload.len() < PREFIX_LEN {
+        return Err(NetError::DecodeError);
+    }
+    let codec = Compression::from_u8(payload[0]).ok_or(NetError::DecodeError)?;
+    let length = u32::from_be_bytes([payload[1], payload[2], payload[3], payload[4]]);
+    if length > max {
+        return Err(NetError::FrameTooLarge { kind, length, max });
+    }
+
+    match decompress_raw(codec, &payload[PREFIX_LEN..], length as usize) {
+        Some(out) if out.len() == length as usize => Ok(out),
+        _ => Err(NetError::DecodeError),
+    }
+}
+
+#[cfg_attr(not(all(feature = "lz4", feature = "zstd")), allow(unused_variables))]
+fn compress_raw(codec: Compression, payload: &[u8]) -> Option<Vec<u8>> {
+    match codec {
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => Some(lz4_flex::block::compress(payload)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::bulk::compress(payload, 0).ok(),
+        // кодек не собран — отправляем как есть
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Decoders write into a buffer of exactly `length` bytes and fail if the
+/// data would need more.
+#[cfg_attr(not(all(feature = "lz4", feature = "zstd")), allow(unused_variables))]
+fn decompress_raw(codec: Compression, data: &[u8], length: usize) -> Option<Vec<u8>> {
+    match codec {
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            let mut out = vec![0u8; length];
+            let n = lz4_flex::block::decompress_into(data, &mut out).ok()?;
+            out.truncate(n);
+            Some(out)
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::bulk::decompress(data, length).ok(),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
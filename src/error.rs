@@ -0,0 +1,189 @@
+use std::fmt;
+use std::io;
+
+/// Errors of the QuarXNet wire layer.
+///
+/// `quarxtor_core::net_core::NetError` only covers the codecs (`InvalidFrame`,
+/// `DecodeError`); transports also need timeouts, peer shutdown and plain
+/// I/O failures, so the protocol works with this extended type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetError {
+    /// Header or frame length does not match the wire format.
+    InvalidFrame,
+    /// Payload could not be decoded.
+    DecodeError,
+    /// Header announced a payload above the configured limit for its kind.
+    /// Raised before the payload is read.
+    FrameTooLarge { kind: u8, length: u32, max: u32 },
+    /// CRC32C trailer did not match the received payload.
+    ChecksumMismatch { kind: u8 },
+    /// The peer answered with an Error frame.
+    Remote { code: ErrorCode, message: String },
+    /// A block in a structured PushBlocks payload does not match its hash.
+    BlockHashMismatch { id: u64 },
+    /// A streamed object's length or hash does not match its End frame.
+    ObjectIntegrity { id: u64 },
+    /// Hello exchange found different protocol majors.
+    IncompatibleVersion { local: u16, remote: u16 },
+    /// A read or write timeout expired.
+    Timeout,
+    /// The peer closed the connection (possibly in the middle of a frame).
+    ConnectionClosed,
+    /// Any other I/O failure.
+    Io(io::ErrorKind),
+    /// Keepalive gave up: this many Pings in a row went unanswered.
+    PeerDead { missed: u32 },
+    /// TLS handshake failed or the TLS setup (certificates, keys) is invalid.
+    Tls(String),
+    /// The peer's authenticated identity is not the node it claims to be.
+    PeerIdentityMismatch { node: u64 },
+    /// Noise handshake failed or a record did not authenticate.
+    Noise(String),
+    /// The peer did not prove knowledge of the cluster secret.
+    ClusterAuthFailed { node: u64 },
+    /// A streamed response to `request_id` has started; it must be read
+    /// with `RpcClient::wait_stream` before other responses.
+    StreamPending { request_id: u32 },
+    /// Local configuration (e.g. a cluster secret) is unusable.
+    Config(String),
+}
+
+pub type NetResult<T> = Result<T, NetError>;
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::InvalidFrame     => write!(f, "invalid frame"),
+            NetError::DecodeError      => write!(f, "payload decode error"),
+            NetError::FrameTooLarge { kind, length, max } => {
+                write!(f, "frame of kind {kind} is {length} bytes, limit is {max}")
+            }
+            NetError::ChecksumMismatch { kind } => {
+                write!(f, "checksum mismatch in frame of kind {kind}")
+            }
+            NetError::Remote { code, message } => {
+                write!(f, "peer error {} ({code:?}): {message}", code.to_u16())
+            }
+            NetError::BlockHashMismatch { id } => write!(f, "block {id} does not match its hash"),
+            NetError::ObjectIntegrity { id } => write!(f, "streamed object {id} is corrupt"),
+            NetError::IncompatibleVersion { local, remote } => {
+                write!(f, "peer speaks protocol {remote}.x, this node speaks {local}.x")
+            }
+            NetError::Timeout          => write!(f, "i/o timeout"),
+            NetError::ConnectionClosed => write!(f, "connection closed by peer"),
+            NetError::Io(kind)         => write!(f, "i/o error: {kind}"),
+            NetError::PeerDead { missed } => {
+                write!(f, "peer unresponsive after {missed} missed pongs")
+            }
+            NetError::Tls(msg)         => write!(f, "tls: {msg}"),
+            NetError::PeerIdentityMismatch { node } => {
+                write!(f, "peer is not authenticated as node {node}")
+            }
+            NetError::Noise(msg)       => write!(f, "noise: {msg}"),
+            NetError::ClusterAuthFailed { node } => {
+                write!(f, "node {node} failed cluster authentication")
+            }
+            NetError::StreamPending { request_id } => {
+                write!(f, "streamed response to request {request_id} must be read first")
+            }
+            NetError::Config(msg)      => write!(f, "invalid configuration: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // таймаут сокета на Unix приходит как WouldBlock
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => NetError::Timeout,
+            io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => NetError::ConnectionClosed,
+            kind => NetError::Io(kind),
+        }
+    }
+}
+
+/// Error codes carried by the Error frame.
+///
+/// Codes are part of the wire format: never renumber, only append.
+/// Codes unknown to this build are preserved as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// Requested object or block does not exist.
+    NotFound,
+    /// Request or the response it would produce exceeds a limit.
+    TooLarge,
+    /// Protocol version of the peer is not supported.
+    UnsupportedVersion,
+    /// Frame kind or feature is not supported by the responder.
+    Unsupported,
+    /// Request frame or payload is malformed.
+    Malformed,
+    /// Responder is overloaded; retry later.
+    Busy,
+    /// Unexpected failure on the responder side.
+    Internal,
+    /// Peer failed authentication.
+    Unauthorized,
+    Other(u16),
+}
+
+impl ErrorCode {
+    pub fn to_u16(self) -> u16 {
+        match self {
+            ErrorCode::NotFound           => 1,
+            ErrorCode::TooLarge           => 2,
+            ErrorCode::UnsupportedVersion => 3,
+            ErrorCode::Unsupported        => 4,
+            ErrorCode::Malformed          => 5,
+            ErrorCode::Busy               => 6,
+            ErrorCode::Internal           => 7,
+            ErrorCode::Unauthorized       => 8,
+            ErrorCode::Other(c)           => c,
+        }
+    }
+
+    pub fn from_u16(c: u16) -> Self {
+        match c {
+            1 => ErrorCode::NotFound,
+            2 => ErrorCode::TooLarge,
+            3 => ErrorCode::UnsupportedVersion,
+            4 => ErrorCode::Unsupported,
+            5 => ErrorCode::Malformed,
+            6 => ErrorCode::Busy,
+            7 => ErrorCode::Internal,
+            8 => ErrorCode::Unauthorized,
+            c => ErrorCode::Other(c),
+        }
+    }
+}
+
+impl From<&NetError> for ErrorCode {
+    /// Code a responder should report to its peer for a local failure.
+    fn from(e: &NetError) -> Self {
+        match e {
+            NetError::FrameTooLarge { .. } => ErrorCode::TooLarge,
+            NetError::InvalidFrame
+            | NetError::DecodeError
+            | NetError::ChecksumMismatch { .. }
+            | NetError::BlockHashMismatch { .. }
+            | NetError::ObjectIntegrity { .. } => ErrorCode::Malformed,
+            NetError::Remote { code, .. } => *code,
+            NetError::IncompatibleVersion { .. } => ErrorCode::UnsupportedVersion,
+            NetError::Timeout
+            | NetError::ConnectionClosed
+            | NetError::Io(_)
+            | NetError::PeerDead { .. }
+            | NetError::Tls(_)
+            | NetError::StreamPending { .. }
+            | NetError::Config(_) => ErrorCode::Internal,
+            NetError::PeerIdentityMismatch { .. }
+            | NetError::Noise(_)
+            | NetError::ClusterAuthFailed { .. } => ErrorCode::Unauthorized,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Frame types of the QuarXNet wire protocol.
+//!
+//! These mirror `quarxtor_core::net_core::{FrameKind, FrameHeader, Frame}`
+//! and convert to and from them, but additionally carry the wire-level
+//! extensions negotiated by this crate (e.g. request ids).
+//!
+//! Payloads are [`Bytes`]: a received payload is a reference-counted slice
+//! of the receive buffer, so it can be passed on without copying.
+
+use bytes::Bytes;
+use quarxtor_core::net_core as core;
+
+use crate::error::{NetError, NetResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameKind {
+    Hello,
+    Caps,
+    GetBlocks,
+    PushBlocks,
+    GetObject,
+    PushObject,
+    Ping,
+    Pong,
+    /// Structured failure report (`protocol::ErrorPayload`). Has no
+    /// counterpart in `quarxtor_core`.
+    Error,
+    /// Planned end of the session (`protocol::GoodbyePayload`). Has no
+    /// counterpart in `quarxtor_core`.
+    Goodbye,
+    /// Peer authentication right after Hello (`protocol::AuthPayload`).
+    /// Has no counterpart in `quarxtor_core`.
+    Auth,
+    /// Any other kind byte: an application kind (`registry::FrameRegistry`)
+    /// or a protocol kind newer than this build.
+    Extension(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub kind: FrameKind,
+    pub flags: u8,
+    /// Payload length, excluding header extensions and trailers.
+    pub length: u32,
+    /// Request/response correlation id (`protocol::FLAG_REQUEST_ID`).
+    pub request_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub header: FrameHeader,
+    pub payload: Bytes,
+}
+
+impl FrameHeader {
+    pub fn new(kind: FrameKind, length: u32) -> Self {
+        FrameHeader { kind, flags: 0, length, request_id: None }
+    }
+}
+
+impl Frame {
+    pub fn new<P: Into<Bytes>>(kind: FrameKind, payload: P) -> Self {
+        let payload = payload.into();
+        Frame { header: FrameHeader::new(kind, payload.len() as u32), payload }
+    }
+
+    pub fn with_request_id(mut self, id: u32) -> Self {
+        self.header.request_id = Some(id);
+        self
+    }
+}
+
+/// -----------------------------
+/// Conversions (quarxtor-core)
+/// -----------------------------
+impl From<core::FrameKind> for FrameKind {
+    fn from(k: core::FrameKind) -> Self {
+        match k {
+            core::FrameKind::Hello      => FrameKind::Hello,
+            core::FrameKind::Caps       => FrameKind::Caps,
+            core::FrameKind::GetBlocks  => FrameKind::GetBlocks,
+            core::FrameKind::PushBlocks => FrameKind::PushBlocks,
+            core::FrameKind::GetObject  => FrameKind::GetObject,
+            core::FrameKind::PushObject => FrameKind::PushObject,
+            core::FrameKind::Ping       => FrameKind::Ping,
+            core::FrameKind::Pong       => FrameKind::Pong,
+        }
+    }
+}
+
+/// Fails for kinds that exist only in QuarXNet.
+impl TryFrom<FrameKind> for core::FrameKind {
+    type Error = NetError;
+
+    fn try_from(k: FrameKind) -> NetResult<Self> {
+        Ok(match k {
+            FrameKind::Hello      => core::FrameKind::Hello,
+            FrameKind::Caps       => core::FrameKind::Caps,
+            FrameKind::GetBlocks  => core::FrameKind::GetBlocks,
+            FrameKind::PushBlocks => core::FrameKind::PushBlocks,
+            FrameKind::GetObject  => core::FrameKind::GetObject,
+            FrameKind::PushObject => core::FrameKind::PushObject,
+            FrameKind::Ping       => core::FrameKind::Ping,
+            FrameKind::Pong       => core::FrameKind::Pong,
+            FrameKind::Error | FrameKind::Goodbye | FrameKind::Auth | FrameKind::Extension(_) => {
+                return Err(NetError::InvalidFrame)
+            }
+        })
+    }
+}
+
+impl From<core::Frame> for Frame {
+    fn from(f: core::Frame) -> Self {
+        Frame {
+            header: FrameHeader {
+                kind: f.header.kind.into(),
+                flags: f.header.flags,
+                length: f.header.length,
+                request_id: None,
+            },
+            payload: f.payload.into(),
+        }
+    }
+}
+
+/// Drops the request id: core frames have no place for it.
+impl TryFrom<Frame> for core::Frame {
+    type Error = NetError;
+
+    fn try_from(f: Frame) -> NetResult<Self> {
+        Ok(core::Frame {
+            header: core::FrameHeader {
+                kind: f.header.kind.try_into()?,
+                flags: f.header.flags,
+                length: f.header.length,
+            },
+            payload: f.payload.into(),
+        })
+    }
+}
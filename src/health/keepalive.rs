@@ -0,0 +1,183 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::latency::{unix_micros, RttSample};
+use crate::error::{NetError, NetResult};
+use crate::frame::{Frame, FrameKind};
+use crate::protocol::{
+    decode_ping, decode_pong, encode_ping, encode_pong, make_frame, make_response, send_frame_with,
+    FrameOptions, PingPayload, PongPayload, Transport,
+};
+
+/// Time source for [`Keepalive`], in µs. Ping timestamps are taken from it
+/// too, so it should track wall-clock time in production.
+pub trait Clock {
+    fn now_us(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_us(&self) -> u64 {
+        unix_micros()
+    }
+}
+
+/// Manually driven clock for tests; clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Arc<AtomicU64>);
+
+impl MockClock {
+    pub fn new(start_us: u64) -> Self {
+        MockClock(Arc::new(AtomicU64::new(start_us)))
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.0.fetch_add(d.as_micros() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_us(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+    /// Send a Ping once nothing has been received for this long.
+    pub idle: Duration,
+    /// A Ping without a Pong after this long counts as missed.
+    pub pong_timeout: Duration,
+    /// Consecutive missed Pongs after which the peer is declared dead.
+    pub max_missed: u32,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        KeepaliveConfig {
+            idle: Duration::from_secs(15),
+            pong_timeout: Duration::from_secs(5),
+            max_missed: 3,
+        }
+    }
+}
+
+/// Keepalive and dead-peer detection for one connection.
+///
+/// Not a thread of its own: the connection's owner calls
+/// [`Keepalive::observe`] for every received frame and [`Keepalive::tick`]
+/// periodically (e.g. whenever a read times out). Any received frame counts
+/// as a sign of life; Pings and Pongs are answered and consumed here.
+pub struct Keepalive<C: Clock = SystemClock> {
+    config: KeepaliveConfig,
+    clock: C,
+    opts: FrameOptions,
+    last_rx_us: u64,
+    outstanding: Option<PingPayload>,
+    missed: u32,
+    next_nonce: u64,
+    last_rtt: Option<RttSample>,
+}
+
+impl Keepalive<SystemClock> {
+    pub fn new(config: KeepaliveConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> Keepalive<C> {
+    pub fn with_clock(config: KeepaliveConfig, clock: C) -> Self {
+        let now = clock.now_us();
+        Keepalive {
+            config,
+            clock,
+            opts: FrameOptions::default(),
+            last_rx_us: now,
+            outstanding: None,
+            missed: 0,
+            next_nonce: 0,
+            last_rtt: None,
+        }
+    }
+
+    /// Frame options used for the Pings and Pongs we send.
+    pub fn with_options(mut self, opts: FrameOptions) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// Consecutive Pings that went unanswered.
+    pub fn missed(&self) -> u32 {
+        self.missed
+    }
+
+    pub fn last_rtt(&self) -> Option<RttSample> {
+        self.last_rtt
+    }
+
+    /// Sends a Ping if the connection has been idle, and fails with
+    /// [`NetError::PeerDead`] once `max_missed` Pings in a row went
+    /// unanswered.
+    pub fn tick<T: Transport>(&mut self, t: &mut T) -> NetResult<()> {
+        let now = self.clock.now_us();
+
+        if let Some(ping) = self.outstanding {
+            if now.saturating_sub(ping.sent_at_us) < self.config.pong_timeout.as_micros() as u64 {
+                return Ok(());
+            }
+            self.outstanding = None;
+            self.missed += 1;
+            if self.missed >= self.config.max_missed {
+                return Err(NetError::PeerDead { missed: self.missed });
+            }
+            // сразу пробуем ещё раз
+            return self.send_ping(t, now);
+        }
+
+        if now.saturating_sub(self.last_rx_us) >= self.config.idle.as_micros() as u64 {
+            return self.send_ping(t, now);
+        }
+        Ok(())
+    }
+
+    fn send_ping<T: Transport>(&mut self, t: &mut T, now: u64) -> NetResult<()> {
+        self.next_nonce = self.next_nonce.wrapping_add(1);
+        let ping = PingPayload { nonce: self.next_nonce, sent_at_us: now };
+        send_frame_with(t, &make_frame(FrameKind::Ping, encode_ping(&ping)), &self.opts)?;
+        self.outstanding = Some(ping);
+        Ok(())
+    }
+
+    /// Records a received frame. Returns `true` if it was a Ping or Pong
+    /// and has been fully handled (a Ping is answered on `t`).
+    pub fn observe<T: Transport>(&mut self, t: &mut T, f: &Frame) -> NetResult<bool> {
+        let now = self.clock.now_us();
+        self.last_rx_us = now;
+        self.missed = 0;
+
+        match f.header.kind {
+            FrameKind::Ping => {
+                // пир без payload (старая версия) — отвечаем пустым Pong
+                let body = match decode_ping(&f.payload) {
+                    Ok(ping) => encode_pong(&PongPayload::answer(&ping, now)),
+                    Err(_) => Vec::new(),
+                };
+                send_frame_with(t, &make_response(f, FrameKind::Pong, body), &self.opts)?;
+                Ok(true)
+            }
+            FrameKind::Pong => {
+                if let Ok(pong) = decode_pong(&f.payload) {
+                    if self.outstanding.map(|p| p.nonce) == Some(pong.nonce) {
+                        self.outstanding = None;
+                        self.last_rtt = Some(RttSample::from_pong(&pong, now));
+                    }
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
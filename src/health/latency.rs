@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::protocol::{PingPayload, PongPayload};
+
+/// Current wall-clock time in µs since the Unix epoch, as used in Ping/Pong.
+pub fn unix_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+/// One Ping/Pong round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RttSample {
+    pub rtt: Duration,
+    /// Estimated `peer clock - local clock` in µs, assuming the Ping took
+    /// half the round trip. Accuracy is bounded by `rtt / 2`.
+    pub clock_offset_us: i64,
+}
+
+impl RttSample {
+    /// Evaluates `pong` arriving at `now_us` (local clock).
+    pub fn from_pong(pong: &PongPayload, now_us: u64) -> Self {
+        let rtt_us = now_us.saturating_sub(pong.ping_sent_at_us);
+        let midpoint = pong.ping_sent_at_us as i128 + (rtt_us / 2) as i128;
+        let offset = pong.received_at_us as i128 - midpoint;
+
+        RttSample {
+            rtt: Duration::from_micros(rtt_us),
+            clock_offset_us: offset.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        }
+    }
+}
+
+/// Smoothed latency of one peer.
+///
+/// RTT and offset are exponentially weighted moving averages (gain 1/8, as
+/// for TCP's SRTT), so a single slow round trip does not reorder replicas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerLatency {
+    pub srtt: Duration,
+    pub clock_offset_us: i64,
+    pub last: RttSample,
+    pub samples: u64,
+}
+
+impl PeerLatency {
+    fn new(s: RttSample) -> Self {
+        PeerLatency { srtt: s.rtt, clock_offset_us: s.clock_offset_us, last: s, samples: 1 }
+    }
+
+    fn update(&mut self, s: RttSample) {
+        let srtt = self.srtt.as_micros() as i128;
+        let rtt = s.rtt.as_micros() as i128;
+        self.srtt = Duration::from_micros((srtt + (rtt - srtt) / 8) as u64);
+
+        let off = self.clock_offset_us as i128;
+        self.clock_offset_us = (off + (s.clock_offset_us as i128 - off) / 8) as i64;
+
+        self.last = s;
+        self.samples += 1;
+    }
+}
+
+/// Latency per peer node, fed from Pongs; also hands out Ping payloads.
+///
+/// A Ping left without a Pong for longer than the pong timeout is
+/// forgotten, so a lossy peer cannot grow the table.
+#[derive(Debug)]
+pub struct LatencyTable {
+    peers: HashMap<u64, PeerLatency>,
+    /// nonce -> (peer, sent at µs)
+    outstanding: HashMap<u64, (u64, u64)>,
+    next_nonce: u64,
+    pong_timeout: Duration,
+}
+
+impl Default for LatencyTable {
+    fn default() -> Self {
+        LatencyTable {
+            peers: HashMap::new(),
+            outstanding: HashMap::new(),
+            next_nonce: 0,
+            pong_timeout: Self::DEFAULT_PONG_TIMEOUT,
+        }
+    }
+}
+
+impl LatencyTable {
+    pub const DEFAULT_PONG_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pong_timeout(mut self, timeout: Duration) -> Self {
+        self.pong_timeout = timeout;
+        self
+    }
+
+    /// Builds the payload for a Ping to `peer` sent at `now_us`. Pings
+    /// that have been waiting longer than the pong timeout are dropped.
+    pub fn ping(&mut self, peer: u64, now_us: u64) -> PingPayload {
+        let timeout = self.pong_timeout.as_micros() as u64;
+        self.outstanding.retain(|_, (_, sent)| now_us.saturating_sub(*sent) <= timeout);
+
+        self.next_nonce = self.next_nonce.wrapping_add(1);
+        self.outstanding.insert(self.next_nonce, (peer, now_us));
+        PingPayload { nonce: self.next_nonce, sent_at_us: now_us }
+    }
+
+    /// Number of Pings still waiting for their Pong.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    /// Records a Pong received at `now_us`. Pongs whose nonce was not
+    /// handed out by [`LatencyTable::ping`] (late duplicates, forgeries) or
+    /// that arrive after the pong timeout are ignored and return `None`.
+    pub fn record_pong(&mut self, pong: &PongPayload, now_us: u64) -> Option<RttSample> {
+        let (peer, sent) = self.outstanding.remove(&pong.nonce)?;
+        if now_us.saturating_sub(sent) > self.pong_timeout.as_micros() as u64 {
+            return None;
+        }
+        let sample = RttSample::from_pong(pong, now_us);
+        self.peers
+            .entry(peer)
+            .and_modify(|p| p.update(sample))
+            .or_insert_with(|| PeerLatency::new(sample));
+        Some(sample)
+    }
+
+    pub fn get(&self, peer: u64) -> Option<&PeerLatency> {
+        self.peers.get(&peer)
+    }
+
+    /// Drops a peer and any Pings still waiting for it.
+    pub fn forget(&mut self, peer: u64) {
+        self.peers.remove(&peer);
+        self.outstanding.retain(|_, (p, _)| *p != peer);
+    }
+
+    /// Among `candidates`, the peer with the lowest smoothed RTT. Peers
+    /// without measurements are skipped.
+    pub fn closest<I: IntoIterator<Item = u64>>(&self, candidates: I) -> Option<u64> {
+        candidates
+            .into_iter()
+            .filter_map(|p| self.peers.get(&p).map(|l| (l.srtt, p)))
+            .min()
+            .map(|(_, p)| p)
+    }
+}
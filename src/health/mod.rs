@@ -0,0 +1,7 @@
+//! Peer health: latency measurement and keepalive over Ping/Pong.
+
+pub mod latency;
+pub mod keepalive;
+
+pub use latency::{unix_micros, LatencyTable, PeerLatency, RttSample};
+pub use keepalive::{Clock, Keepalive, KeepaliveConfig, MockClock, SystemClock};
@@ -0,0 +1,183 @@
+//! Node identity keys and the signed Hello (feature `identity`).
+//!
+//! Every node owns an ed25519 [`NodeKeypair`]; its node id is derived from
+//! the public key ([`node_id`]), so a peer can only claim the id that
+//! belongs to a key it holds. [`signed_handshake`] replaces the plain
+//! Hello exchange:
+//!
+//! 1. each side sends a Hello extended with its public key and a fresh
+//!    random nonce ([`SignedHello`]);
+//! 2. each side checks that the peer's node id matches its key, then sends
+//!    an Auth frame ([`AuthMethod::Signature`]) signing both Hellos;
+//! 3. each side verifies the peer's signature. The transcript contains its
+//!    own nonce, so a signature recorded on another connection is useless.
+//!    A peer presenting our own key is refused, so our own Hello and
+//!    signature cannot be reflected back to us.
+//!
+//! Peers that only understand plain Hello decode the extended payload as a
+//! plain one (extra bytes are ignored), but a signed handshake refuses them.
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+use crate::error::{ErrorCode, NetError, NetResult};
+use crate::frame::{Frame, FrameKind};
+use crate::protocol::{
+    accept_hello, check_remote_error, decode_auth, decode_hello, encode_auth, encode_hello,
+    make_frame, random_bytes, recv_frame, recv_hello, refuse, send_frame, AuthMethod, AuthPayload,
+    Session, Transport,
+};
+
+pub use crate::node_key::node_id;
+
+const SIGNATURE_CONTEXT: &[u8] = b"quarxnet signed hello v1";
+const NONCE_LEN: usize = 32;
+const HELLO_LEN: usize = 12;
+const SIGNED_HELLO_LEN: usize = HELLO_LEN + 32 + NONCE_LEN;
+
+/// Long-term ed25519 key pair of a node.
+#[derive(Clone)]
+pub struct NodeKeypair {
+    signing: SigningKey,
+}
+
+impl NodeKeypair {
+    pub fn generate() -> NetResult<Self> {
+        Ok(Self::from_secret_key(&random_bytes()?))
+    }
+
+    /// Restores a key pair saved with [`NodeKeypair::secret_key`].
+    pub fn from_secret_key(secret: &[u8; 32]) -> Self {
+        NodeKeypair { signing: SigningKey::from_bytes(secret) }
+    }
+
+    pub fn secret_key(&self) -> [u8; 32] {
+        self.signing.to_bytes()
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing.verifying_key().to_bytes()
+    }
+
+    pub fn node_id(&self) -> u64 {
+        node_id(&self.public_key())
+    }
+}
+
+impl std::fmt::Debug for NodeKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeKeypair").field("public", &self.public_key()).finish_non_exhaustive()
+    }
+}
+
+/// Hello extended with the sender's public key and the nonce the peer has
+/// to sign.
+pub struct SignedHello {
+    pub hello: HelloPayload,
+    pub public_key: [u8; 32],
+    pub nonce: [u8; 32],
+}
+
+impl SignedHello {
+    /// Hello for `keys` with a fresh nonce; the node id is derived from the
+    /// key.
+    pub fn new(keys: &NodeKeypair, version: ProtocolVersion) -> NetResult<Self> {
+        Ok(SignedHello {
+            hello: HelloPayload { node: keys.node_id(), version },
+            public_key: keys.public_key(),
+            nonce: random_bytes()?,
+        })
+    }
+}
+
+/// Signed Hello: the plain Hello encoding, then the 32-byte public key and
+/// the 32-byte nonce.
+pub fn encode_signed_hello(p: &SignedHello) -> Vec<u8> {
+    let mut v = encode_hello(&p.hello);
+    v.extend_from_slice(&p.public_key);
+    v.extend_from_slice(&p.nonce);
+    v
+}
+
+/// Fails with `DecodeError` for a plain Hello.
+pub fn decode_signed_hello(b: &[u8]) -> NetResult<SignedHello> {
+    if b.len() < SIGNED_HELLO_LEN {
+        return Err(NetError::DecodeError);
+    }
+    Ok(SignedHello {
+        hello: decode_hello(b)?,
+        public_key: b[HELLO_LEN..HELLO_LEN + 32].try_into().unwrap(),
+        nonce: b[HELLO_LEN + 32..SIGNED_HELLO_LEN].try_into().unwrap(),
+    })
+}
+
+/// What a signature covers: the signer's Hello, then the verifier's Hello
+/// (which holds the verifier's nonce), both as sent on the wire.
+fn transcript(signer: &[u8], verifier: &[u8]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, signer, verifier].concat()
+}
+
+/// Hello exchange that also proves both peers' identities.
+///
+/// Runs [`crate::protocol::handshake`]'s version check, then fails with
+/// [`NetError::PeerIdentityMismatch`] if the peer sent a plain Hello, its
+/// node id does not belong to its key or is our own, or its signature does
+/// not verify.
+/// With `notify_peer` an Error frame (`Unauthorized` or
+/// `UnsupportedVersion`) is sent first. On success
+/// [`Session::peer_public_key`] holds the peer's verified key.
+pub fn signed_handshake<T: Transport>(
+    t: &mut T,
+    keys: &NodeKeypair,
+    version: ProtocolVersion,
+    notify_peer: bool,
+) -> NetResult<Session> {
+    let local = SignedHello::new(keys, version)?;
+    let local_bytes = encode_signed_hello(&local);
+    send_frame(t, &make_frame(FrameKind::Hello, local_bytes.clone()))?;
+
+    let hello = recv_hello(t)?;
+    let mut session = accept_hello(t, &hello, &local.hello, notify_peer)?;
+    let node = session.peer_node;
+
+    // свой же ключ у пира — это наш Hello, отражённый обратно: транскрипт
+    // совпал бы с нашим, и наша же подпись прошла бы проверку
+    let remote = match decode_signed_hello(&hello.payload) {
+        Ok(r) if node_id(&r.public_key) == node && r.public_key != local.public_key => r,
+        _ => return Err(reject(t, &hello, node, notify_peer)),
+    };
+
+    let signature = keys.signing.sign(&transcript(&local_bytes, &hello.payload));
+    let proof = AuthPayload { method: AuthMethod::Signature, data: signature.to_bytes().to_vec() };
+    send_frame(t, &make_frame(FrameKind::Auth, encode_auth(&proof)))?;
+
+    let frame = check_remote_error(recv_frame(t)?)?;
+    if frame.header.kind != FrameKind::Auth {
+        return Err(NetError::InvalidFrame);
+    }
+    let proof = decode_auth(&frame.payload)?;
+    let verified = proof.method == AuthMethod::Signature
+        && verify(&remote.public_key, &transcript(&hello.payload, &local_bytes), &proof.data);
+    if !verified {
+        return Err(reject(t, &frame, node, notify_peer));
+    }
+
+    session.peer_key = Some(remote.public_key);
+    Ok(session)
+}
+
+fn verify(public_key: &[u8; 32], msg: &[u8], signature: &[u8]) -> bool {
+    let Ok(key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    key.verify_strict(msg, &signature).is_ok()
+}
+
+fn reject<T: Transport>(t: &mut T, frame: &Frame, node: u64, notify_peer: bool) -> NetError {
+    let msg = format!("node {node} failed identity verification");
+    let err = NetError::PeerIdentityMismatch { node };
+    refuse(t, frame, ErrorCode::Unauthorized, &msg, err, notify_peer)
+}
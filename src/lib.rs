@@ -1,4 +1,16 @@
+pub mod error;
+pub mod frame;
 pub mod protocol;
+pub mod message;
+pub mod registry;
 pub mod capability;
+pub mod compress;
+pub mod stream;
 pub mod transport;
 pub mod sync;
+pub mod health;
+pub mod node_key;
+#[cfg(feature = "identity")]
+pub mod identity;
+#[cfg(feature = "cluster-auth")]
+pub mod cluster_auth;
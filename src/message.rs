@@ -0,0 +1,222 @@
+//! Typed view of frames: one [`Message`] variant per frame kind, each
+//! carrying its decoded payload.
+//!
+//! [`Message::decode`] picks the codec from the frame kind, so a payload can
+//! no longer be paired with the wrong `decode_*` function. Payloads use the
+//! base encodings; negotiated variants (`GetBlockRanges`, `BlockBatch`,
+//! object streams) are still decoded from the raw frame with their own
+//! codecs.
+
+use std::fmt;
+
+use quarxtor_core::net_core::{
+    GetBlocksPayload, GetObjectPayload, HelloPayload, ProtocolVersion, PushBlocksPayload,
+    PushObjectPayload,
+};
+
+use crate::capability::CapabilitySet;
+use crate::error::NetResult;
+use crate::frame::{Frame, FrameKind};
+use crate::protocol::{
+    decode_auth, decode_caps, decode_error, decode_get_blocks, decode_get_object,
+    decode_goodbye, decode_hello, decode_ping, decode_pong, decode_push_blocks,
+    decode_push_object, encode_auth, encode_caps, encode_error, encode_get_blocks,
+    encode_get_object, encode_goodbye, encode_hello, encode_ping, encode_pong,
+    encode_push_blocks, encode_push_object, recv_frame_with_limits, send_frame_with,
+    AuthPayload, ErrorPayload, FrameLimits, FrameOptions, GoodbyePayload, PingPayload,
+    PongPayload, Transport,
+};
+
+/// A frame's decoded payload, tagged by frame kind.
+///
+/// `Debug`, `Clone` and `PartialEq` are implemented by hand: the core
+/// payload types do not derive them. Block and object data are shown by
+/// length only.
+pub enum Message {
+    Hello(HelloPayload),
+    Caps(CapabilitySet),
+    GetBlocks(GetBlocksPayload),
+    PushBlocks(PushBlocksPayload),
+    GetObject(GetObjectPayload),
+    PushObject(PushObjectPayload),
+    Ping(PingPayload),
+    Pong(PongPayload),
+    Error(ErrorPayload),
+    Goodbye(GoodbyePayload),
+    Auth(AuthPayload),
+    /// Application kind (`crate::registry`); decode it with its
+    /// `ExtensionFrame` impl.
+    Extension(Frame),
+}
+
+impl Message {
+    pub fn kind(&self) -> FrameKind {
+        match self {
+            Message::Hello(_)      => FrameKind::Hello,
+            Message::Caps(_)       => FrameKind::Caps,
+            Message::GetBlocks(_)  => FrameKind::GetBlocks,
+            Message::PushBlocks(_) => FrameKind::PushBlocks,
+            Message::GetObject(_)  => FrameKind::GetObject,
+            Message::PushObject(_) => FrameKind::PushObject,
+            Message::Ping(_)       => FrameKind::Ping,
+            Message::Pong(_)       => FrameKind::Pong,
+            Message::Error(_)      => FrameKind::Error,
+            Message::Goodbye(_)    => FrameKind::Goodbye,
+            Message::Auth(_)       => FrameKind::Auth,
+            Message::Extension(f)  => f.header.kind,
+        }
+    }
+
+    /// Frame carrying this message, without flags or request id (an
+    /// `Extension` frame is returned as is).
+    pub fn encode(&self) -> Frame {
+        let payload = match self {
+            Message::Hello(p)      => encode_hello(p),
+            Message::Caps(c)       => encode_caps(c),
+            Message::GetBlocks(p)  => encode_get_blocks(p),
+            Message::PushBlocks(p) => encode_push_blocks(p),
+            Message::GetObject(p)  => encode_get_object(p),
+            Message::PushObject(p) => encode_push_object(p),
+            Message::Ping(p)       => encode_ping(p),
+            Message::Pong(p)       => encode_pong(p),
+            Message::Error(p)      => encode_error(p),
+            Message::Goodbye(p)    => encode_goodbye(p),
+            Message::Auth(p)       => encode_auth(p),
+            Message::Extension(f)  => return f.clone(),
+        };
+        Frame::new(self.kind(), payload)
+    }
+
+    /// Decodes the payload with the codec for the frame's kind. Header
+    /// flags and request id are not part of the message; read them from the
+    /// frame first if needed.
+    pub fn decode(f: Frame) -> NetResult<Self> {
+        let b = &f.payload;
+        Ok(match f.header.kind {
+            FrameKind::Hello      => Message::Hello(decode_hello(b)?),
+            FrameKind::Caps       => Message::Caps(decode_caps(b)?),
+            FrameKind::GetBlocks  => Message::GetBlocks(decode_get_blocks(b)?),
+            FrameKind::PushBlocks => Message::PushBlocks(decode_push_blocks(b)?),
+            FrameKind::GetObject  => Message::GetObject(decode_get_object(b)?),
+            FrameKind::PushObject => Message::PushObject(decode_push_object(b)?),
+            FrameKind::Ping       => Message::Ping(decode_ping(b)?),
+            FrameKind::Pong       => Message::Pong(decode_pong(b)?),
+            FrameKind::Error      => Message::Error(decode_error(b)?),
+            FrameKind::Goodbye    => Message::Goodbye(decode_goodbye(b)?),
+            FrameKind::Auth       => Message::Auth(decode_auth(b)?),
+            FrameKind::Extension(_) => Message::Extension(f),
+        })
+    }
+}
+
+fn clone_hello(h: &HelloPayload) -> HelloPayload {
+    let version = ProtocolVersion { major: h.version.major, minor: h.version.minor };
+    HelloPayload { node: h.node, version }
+}
+
+impl Clone for Message {
+    fn clone(&self) -> Self {
+        match self {
+            Message::Hello(h)      => Message::Hello(clone_hello(h)),
+            Message::Caps(c)       => Message::Caps(c.clone()),
+            Message::GetBlocks(p)  => Message::GetBlocks(GetBlocksPayload { ids: p.ids.clone() }),
+            Message::PushBlocks(p) => {
+                Message::PushBlocks(PushBlocksPayload { raw: p.raw.clone() })
+            }
+            Message::GetObject(p)  => Message::GetObject(GetObjectPayload { id: p.id }),
+            Message::PushObject(p) => {
+                Message::PushObject(PushObjectPayload { raw: p.raw.clone() })
+            }
+            Message::Ping(p)       => Message::Ping(*p),
+            Message::Pong(p)       => Message::Pong(*p),
+            Message::Error(p)      => Message::Error(p.clone()),
+            Message::Goodbye(p)    => Message::Goodbye(p.clone()),
+            Message::Auth(p)       => Message::Auth(p.clone()),
+            Message::Extension(f)  => Message::Extension(f.clone()),
+        }
+    }
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Message::Hello(a), Message::Hello(b)) => {
+                a.node == b.node
+                    && a.version.major == b.version.major
+                    && a.version.minor == b.version.minor
+            }
+            (Message::Caps(a), Message::Caps(b))             => a == b,
+            (Message::GetBlocks(a), Message::GetBlocks(b))   => a.ids == b.ids,
+            (Message::PushBlocks(a), Message::PushBlocks(b)) => a.raw == b.raw,
+            (Message::GetObject(a), Message::GetObject(b))   => a.id == b.id,
+            (Message::PushObject(a), Message::PushObject(b)) => a.raw == b.raw,
+            (Message::Ping(a), Message::Ping(b))             => a == b,
+            (Message::Pong(a), Message::Pong(b))             => a == b,
+            (Message::Error(a), Message::Error(b))           => a == b,
+            (Message::Goodbye(a), Message::Goodbye(b))       => a == b,
+            (Message::Auth(a), Message::Auth(b))             => a == b,
+            (Message::Extension(a), Message::Extension(b))   => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Message {}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Hello(h) => f
+                .debug_struct("Hello")
+                .field("node", &h.node)
+                .field("version", &format_args!("{}.{}", h.version.major, h.version.minor))
+                .finish(),
+            Message::Caps(c) => f.debug_tuple("Caps").field(c).finish(),
+            Message::GetBlocks(p) => f.debug_struct("GetBlocks").field("ids", &p.ids).finish(),
+            Message::PushBlocks(p) => {
+                f.debug_struct("PushBlocks").field("len", &p.raw.len()).finish()
+            }
+            Message::GetObject(p) => f.debug_struct("GetObject").field("id", &p.id).finish(),
+            Message::PushObject(p) => {
+                f.debug_struct("PushObject").field("len", &p.raw.len()).finish()
+            }
+            Message::Ping(p) => f.debug_tuple("Ping").field(p).finish(),
+            Message::Pong(p) => f.debug_tuple("Pong").field(p).finish(),
+            Message::Error(p) => f.debug_tuple("Error").field(p).finish(),
+            Message::Goodbye(p) => f.debug_tuple("Goodbye").field(p).finish(),
+            Message::Auth(p) => f.debug_tuple("Auth").field(p).finish(),
+            Message::Extension(fr) => f.debug_tuple("Extension").field(fr).finish(),
+        }
+    }
+}
+
+impl TryFrom<Frame> for Message {
+    type Error = crate::error::NetError;
+
+    fn try_from(f: Frame) -> NetResult<Self> {
+        Message::decode(f)
+    }
+}
+
+impl From<&Message> for Frame {
+    fn from(m: &Message) -> Self {
+        m.encode()
+    }
+}
+
+pub fn send_message<T: Transport>(t: &mut T, m: &Message) -> NetResult<()> {
+    send_message_with(t, m, &FrameOptions::default())
+}
+
+pub fn send_message_with<T: Transport>(t: &mut T, m: &Message, opts: &FrameOptions) -> NetResult<()> {
+    send_frame_with(t, &m.encode(), opts)
+}
+
+/// Receives one frame (enforcing [`FrameLimits::default`]) and decodes it.
+pub fn recv_message<T: Transport>(t: &mut T) -> NetResult<Message> {
+    recv_message_with_limits(t, &FrameLimits::default())
+}
+
+pub fn recv_message_with_limits<T: Transport>(t: &mut T, limits: &FrameLimits) -> NetResult<Message> {
+    Message::decode(recv_frame_with_limits(t, limits)?)
+}
@@ -0,0 +1,9 @@
+//! Node ids derived from public keys, shared by the `identity` and `noise`
+//! features.
+
+/// Node id bound to a public key (ed25519 for `identity`, X25519 for
+/// Noise): the first 8 bytes of its BLAKE3 hash, big-endian.
+pub fn node_id(public_key: &[u8; 32]) -> u64 {
+    let h = blake3::hash(public_key);
+    u64::from_be_bytes(h.as_bytes()[..8].try_into().unwrap())
+}
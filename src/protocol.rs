@@ -1,16 +1,42 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::ops::Range;
+use std::time::Duration;
+
+use bytes::{Buf, Bytes, BytesMut};
 use quarxtor_core::net_core::{
-    FrameKind, FrameHeader, Frame,
     HelloPayload, GetBlocksPayload, PushBlocksPayload,
     GetObjectPayload, PushObjectPayload,
-    ProtocolVersion, NetError, NetResult,
+    ProtocolVersion,
 };
 
+use crate::capability::{negotiate, Capability, CapabilitySet};
+use crate::compress::{compress, decompress, Compression};
+use crate::error::{ErrorCode, NetError, NetResult};
+use crate::frame::{Frame, FrameHeader, FrameKind};
+use crate::registry::FrameRegistry;
+use crate::stream::{ObjectStreamReceiver, StreamProgress, StreamSummary};
+
 /// -----------------------------
 /// Transport Trait (абстракция)
 /// -----------------------------
 pub trait Transport {
     fn send(&mut self, data: &[u8]) -> NetResult<()>;
     fn recv_exact(&mut self, len: usize) -> NetResult<Vec<u8>>;
+
+    /// Sends `parts` back to back, as a single `send`. The default joins
+    /// them into one buffer; transports that can write borrowed slices
+    /// directly should override it.
+    fn send_vectored(&mut self, parts: &[&[u8]]) -> NetResult<()> {
+        self.send(&parts.concat())
+    }
+
+    /// Fills `buf` completely. The default goes through `recv_exact`;
+    /// transports that can read into the caller's buffer should override it.
+    fn recv_exact_into(&mut self, buf: &mut [u8]) -> NetResult<()> {
+        buf.copy_from_slice(&self.recv_exact(buf.len())?);
+        Ok(())
+    }
 }
 
 /// -----------------------------
@@ -35,10 +61,8 @@ fn decode_u64(b: &[u8]) -> u64 {
 /// FrameHeader encode/decode (свободные функции)
 /// -----------------------------
 
-fn encode_frame_header(h: &FrameHeader) -> Vec<u8> {
-    let mut v = Vec::with_capacity(1 + 1 + 4);
-
-    let kind_byte = match h.kind {
+pub(crate) fn frame_kind_byte(k: &FrameKind) -> u8 {
+    match k {
         FrameKind::Hello      => 1,
         FrameKind::Caps       => 2,
         FrameKind::GetBlocks  => 3,
@@ -47,9 +71,17 @@ fn encode_frame_header(h: &FrameHeader) -> Vec<u8> {
         FrameKind::PushObject => 6,
         FrameKind::Ping       => 7,
         FrameKind::Pong       => 8,
-    };
+        FrameKind::Error      => 9,
+        FrameKind::Goodbye    => 10,
+        FrameKind::Auth       => 11,
+        FrameKind::Extension(b) => *b,
+    }
+}
+
+fn encode_frame_header(h: &FrameHeader) -> Vec<u8> {
+    let mut v = Vec::with_capacity(1 + 1 + 4);
 
-    v.push(kind_byte);
+    v.push(frame_kind_byte(&h.kind));
     v.push(h.flags);
     v.extend_from_slice(&encode_u32(h.length));
     v
@@ -69,28 +101,311 @@ fn decode_frame_header(buf: &[u8]) -> NetResult<FrameHeader> {
         6 => FrameKind::PushObject,
         7 => FrameKind::Ping,
         8 => FrameKind::Pong,
-        _ => return Err(NetError::InvalidFrame),
+        9 => FrameKind::Error,
+        10 => FrameKind::Goodbye,
+        11 => FrameKind::Auth,
+        0 => return Err(NetError::InvalidFrame),
+        b => FrameKind::Extension(b),
     };
 
     Ok(FrameHeader {
         kind,
        flags: buf[1],
        length: decode_u32(&buf[2..6]),
+       request_id: None,
     })
 }
 
+/// -----------------------------
+/// Header flags
+/// -----------------------------
+/// Payload is followed by a 4-byte big-endian CRC32C of everything between
+/// the header and the trailer. `FrameHeader.length` does not include it.
+pub const FLAG_CHECKSUM: u8 = 0x01;
+/// A 4-byte big-endian request id sits between the header and the payload.
+/// Set automatically from `FrameHeader.request_id`; not counted in `length`.
+pub const FLAG_REQUEST_ID: u8 = 0x02;
+/// PushObject frame is part of a chunked object stream (see `crate::stream`).
+pub const FLAG_STREAM: u8 = 0x04;
+/// More frames of the same stream follow; cleared on the End frame.
+pub const FLAG_MORE: u8 = 0x08;
+/// Receivers that do not know the frame's kind drop it instead of failing
+/// (see `crate::registry`). Has no effect on known kinds.
+pub const FLAG_IGNORABLE: u8 = 0x10;
+/// Payload is compressed (see `crate::compress`). Set by the sender from
+/// `FrameOptions` and removed by the receiver; `length` counts the
+/// compressed payload.
+pub const FLAG_COMPRESSED: u8 = 0x20;
+
+/// Per-connection send options, derived from the negotiated capabilities.
+///
+/// Receivers act on the header flags alone, so only the sending side needs
+/// to know what was negotiated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameOptions {
+    /// Append a CRC32C trailer to every frame.
+    pub checksum: bool,
+    /// Compress payloads with this codec.
+    pub compression: Option<Compression>,
+    /// Payloads shorter than this are sent uncompressed.
+    pub compress_threshold: usize,
+}
+
+impl FrameOptions {
+    pub const DEFAULT_COMPRESS_THRESHOLD: usize = 1024;
+
+    pub fn negotiated(caps: &CapabilitySet) -> Self {
+        FrameOptions {
+            checksum: caps.contains(Capability::CRC32C),
+            compression: Compression::preferred(caps),
+            ..Self::default()
+        }
+    }
+
+    /// Wire payload for `payload` if these options compress it.
+    fn compress(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        match self.compression {
+            Some(codec) if payload.len() >= self.compress_threshold => compress(codec, payload),
+            _ => None,
+        }
+    }
+}
+
+impl Default for FrameOptions {
+    fn default() -> Self {
+        FrameOptions {
+            checksum: false,
+            compression: None,
+            compress_threshold: Self::DEFAULT_COMPRESS_THRESHOLD,
+        }
+    }
+}
+
+/// -----------------------------
+/// Frame size limits
+/// -----------------------------
+/// Upper bounds on `FrameHeader.length`, checked right after the header is
+/// decoded and before any payload buffer is allocated, so a peer cannot make
+/// us reserve gigabytes with a single 6-byte header.
+///
+/// Keep one instance per connection; kinds without an explicit limit fall
+/// back to `default_max`. Extension kinds are accepted only if registered
+/// (or flagged ignorable, in which case they are skipped).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameLimits {
+    default_max: u32,
+    per_kind: BTreeMap<u8, u32>,
+    registry: FrameRegistry,
+}
+
+impl FrameLimits {
+    pub const DEFAULT_MAX: u32 = 16 * 1024 * 1024;
+    /// Caps entry of [`FrameLimits::default`].
+    pub const DEFAULT_CAPS_MAX: u32 = 64 * 1024;
+    /// GetBlocks entry of [`FrameLimits::default`].
+    pub const DEFAULT_GET_BLOCKS_MAX: u32 = 1024 * 1024;
+
+    /// A single limit for every kind, with no per-kind overrides.
+    pub fn uniform(max: u32) -> Self {
+        FrameLimits { default_max: max, per_kind: BTreeMap::new(), registry: FrameRegistry::new() }
+    }
+
+    pub fn with_default_max(mut self, max: u32) -> Self {
+        self.default_max = max;
+        self
+    }
+
+    pub fn with_kind_max(mut self, kind: FrameKind, max: u32) -> Self {
+        self.per_kind.insert(frame_kind_byte(&kind), max);
+        self
+    }
+
+    /// Application kinds to accept. A registered kind's `max_len` applies
+    /// unless overridden with [`FrameLimits::with_kind_max`].
+    pub fn with_registry(mut self, registry: FrameRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub fn registry(&self) -> &FrameRegistry {
+        &self.registry
+    }
+
+    pub fn max_for(&self, kind: &FrameKind) -> u32 {
+        self.max_for_byte(frame_kind_byte(kind))
+    }
+
+    fn max_for_byte(&self, kind: u8) -> u32 {
+        self.per_kind
+            .get(&kind)
+            .copied()
+            .or_else(|| self.registry.get(kind).map(|k| k.max_len))
+            .unwrap_or(self.default_max)
+    }
+
+    /// Kind this node does not understand. Such frames pass [`check`] only
+    /// with [`FLAG_IGNORABLE`], and their bodies are then read and dropped.
+    ///
+    /// [`check`]: FrameLimits::check
+    fn is_unknown(&self, h: &FrameHeader) -> bool {
+        matches!(h.kind, FrameKind::Extension(b) if !self.registry.contains(b))
+    }
+
+    pub fn check(&self, h: &FrameHeader) -> NetResult<()> {
+        if self.is_unknown(h) && h.flags & FLAG_IGNORABLE == 0 {
+            return Err(NetError::InvalidFrame);
+        }
+        let kind = frame_kind_byte(&h.kind);
+        let max = self.max_for_byte(kind);
+        if h.length > max {
+            return Err(NetError::FrameTooLarge { kind, length: h.length, max });
+        }
+        Ok(())
+    }
+}
+
+impl Default for FrameLimits {
+    /// Control frames are tiny; only the data-carrying kinds get room.
+    fn default() -> Self {
+        FrameLimits::uniform(Self::DEFAULT_MAX)
+            .with_kind_max(FrameKind::Hello, 256)
+            .with_kind_max(FrameKind::Caps, Self::DEFAULT_CAPS_MAX)
+            .with_kind_max(FrameKind::GetBlocks, Self::DEFAULT_GET_BLOCKS_MAX)
+            .with_kind_max(FrameKind::PushBlocks, 64 * 1024 * 1024)
+            .with_kind_max(FrameKind::GetObject, 256)
+            .with_kind_max(FrameKind::PushObject, 64 * 1024 * 1024)
+            .with_kind_max(FrameKind::Ping, 256)
+            .with_kind_max(FrameKind::Pong, 256)
+            .with_kind_max(FrameKind::Error, 64 * 1024)
+            .with_kind_max(FrameKind::Goodbye, 64 * 1024)
+            .with_kind_max(FrameKind::Auth, 256)
+    }
+}
+
 /// -----------------------------
 /// Frame encode/decode (свободные функции)
 /// -----------------------------
 
-fn encode_frame(frame: &Frame) -> Vec<u8> {
-    let mut res = Vec::new();
-    res.extend_from_slice(&encode_frame_header(&frame.header));
-    res.extend_from_slice(&frame.payload);
-    res
+/// Everything a frame puts on the wire around its payload: header plus
+/// request id, and the CRC trailer.
+struct WireParts {
+    prefix: [u8; 10],
+    prefix_len: usize,
+    trailer: Option<[u8; 4]>,
+}
+
+/// A payload the u32 length field cannot describe. `length` saturates.
+fn payload_too_large(kind: &FrameKind) -> NetError {
+    NetError::FrameTooLarge { kind: frame_kind_byte(kind), length: u32::MAX, max: u32::MAX }
 }
 
-fn decode_frame(header: FrameHeader, payload: Vec<u8>) -> NetResult<Frame> {
+impl WireParts {
+    /// `payload` is what goes on the wire; `compressed` says it is the
+    /// output of [`FrameOptions::compress`] rather than the frame's own.
+    fn new(
+        header: &FrameHeader,
+        payload: &[u8],
+        compressed: bool,
+        opts: &FrameOptions,
+    ) -> NetResult<Self> {
+        if payload.len() > u32::MAX as usize {
+            return Err(payload_too_large(&header.kind));
+        }
+        let hdr = encode_frame_header(header);
+        let mut prefix = [0u8; 10];
+        prefix[..6].copy_from_slice(&hdr);
+        // служебные флаги определяются опциями соединения и заголовком,
+        // а не тем, что лежит в `flags`
+        prefix[1] &= !(FLAG_CHECKSUM | FLAG_REQUEST_ID | FLAG_COMPRESSED);
+        if opts.checksum {
+            prefix[1] |= FLAG_CHECKSUM;
+        }
+        if compressed {
+            prefix[1] |= FLAG_COMPRESSED;
+            prefix[2..6].copy_from_slice(&encode_u32(payload.len() as u32));
+        }
+        let mut prefix_len = 6;
+        if let Some(id) = header.request_id {
+            prefix[1] |= FLAG_REQUEST_ID;
+            prefix[6..10].copy_from_slice(&encode_u32(id));
+            prefix_len = 10;
+        }
+
+        let trailer = opts.checksum.then(|| {
+            let crc = crc32c::crc32c_append(crc32c::crc32c(&prefix[6..prefix_len]), payload);
+            encode_u32(crc)
+        });
+        Ok(WireParts { prefix, prefix_len, trailer })
+    }
+
+    fn prefix(&self) -> &[u8] {
+        &self.prefix[..self.prefix_len]
+    }
+
+    fn trailer(&self) -> &[u8] {
+        self.trailer.as_ref().map_or(&[], |t| &t[..])
+    }
+}
+
+/// Appends the wire encoding of `frame` to `out`. Reusing `out` across
+/// frames avoids an allocation per frame. Fails with
+/// [`NetError::FrameTooLarge`] for a payload of 4 GiB or more.
+pub fn encode_frame_into(frame: &Frame, opts: &FrameOptions, out: &mut Vec<u8>) -> NetResult<()> {
+    let compressed = opts.compress(&frame.payload);
+    let payload = compressed.as_deref().unwrap_or(&frame.payload);
+    let parts = WireParts::new(&frame.header, payload, compressed.is_some(), opts)?;
+    out.reserve(parts.prefix_len + payload.len() + 4);
+    out.extend_from_slice(parts.prefix());
+    out.extend_from_slice(payload);
+    out.extend_from_slice(parts.trailer());
+    Ok(())
+}
+
+/// Bytes that follow the 6-byte header on the wire: header extensions,
+/// payload and trailer.
+fn body_len(h: &FrameHeader) -> usize {
+    let prefix = if h.flags & FLAG_REQUEST_ID != 0 { 4 } else { 0 };
+    let trailer = if h.flags & FLAG_CHECKSUM != 0 { 4 } else { 0 };
+    prefix + h.length as usize + trailer
+}
+
+/// Takes the body as read from the wire (see [`body_len`]), verifies and
+/// strips the trailer and header extensions, then validates the frame as
+/// usual. A compressed payload is restored within the kind's limit.
+fn finish_frame(mut header: FrameHeader, mut body: Bytes, limits: &FrameLimits) -> NetResult<Frame> {
+    if header.flags & FLAG_CHECKSUM != 0 {
+        if body.len() < 4 {
+            return Err(NetError::InvalidFrame);
+        }
+        let at = body.len() - 4;
+        let expected = decode_u32(&body[at..]);
+        body.truncate(at);
+        if crc32c::crc32c(&body) != expected {
+            return Err(NetError::ChecksumMismatch { kind: frame_kind_byte(&header.kind) });
+        }
+        header.flags &= !FLAG_CHECKSUM;
+    }
+    if header.flags & FLAG_REQUEST_ID != 0 {
+        if body.len() < 4 {
+            return Err(NetError::InvalidFrame);
+        }
+        header.request_id = Some(decode_u32(&body[..4]));
+        body.advance(4);
+        header.flags &= !FLAG_REQUEST_ID;
+    }
+    if header.flags & FLAG_COMPRESSED != 0 {
+        if body.len() != header.length as usize {
+            return Err(NetError::InvalidFrame);
+        }
+        let kind = frame_kind_byte(&header.kind);
+        body = decompress(&body, kind, limits.max_for_byte(kind))?.into();
+        header.length = body.len() as u32;
+        header.flags &= !FLAG_COMPRESSED;
+    }
+    decode_frame(header, body)
+}
+
+fn decode_frame(header: FrameHeader, payload: Bytes) -> NetResult<Frame> {
     if payload.len() != header.length as usize {
         return Err(NetError::InvalidFrame);
     }
@@ -122,6 +437,49 @@ pub fn decode_hello(b: &[u8]) -> NetResult<HelloPayload> {
     })
 }
 
+/// Caps: u64 known bits, u16 extension count, then per extension
+/// u16 length + UTF-8 name.
+pub fn encode_caps(c: &CapabilitySet) -> Vec<u8> {
+    let mut v = Vec::new();
+    v.extend_from_slice(&encode_u64(c.bits()));
+
+    let names: Vec<&str> = c.extensions().collect();
+    v.extend_from_slice(&encode_u16(names.len() as u16));
+    for name in names {
+        v.extend_from_slice(&encode_u16(name.len() as u16));
+        v.extend_from_slice(name.as_bytes());
+    }
+    v
+}
+
+pub fn decode_caps(b: &[u8]) -> NetResult<CapabilitySet> {
+    if b.len() < 10 {
+        return Err(NetError::DecodeError);
+    }
+    let bits = decode_u64(&b[0..8]);
+    let count = decode_u16(&b[8..10]) as usize;
+
+    let mut names = Vec::with_capacity(count);
+    let mut rest = &b[10..];
+    for _ in 0..count {
+        if rest.len() < 2 {
+            return Err(NetError::DecodeError);
+        }
+        let len = decode_u16(&rest[0..2]) as usize;
+        if rest.len() < 2 + len {
+            return Err(NetError::DecodeError);
+        }
+        let name = std::str::from_utf8(&rest[2..2 + len]).map_err(|_| NetError::DecodeError)?;
+        names.push(name);
+        rest = &rest[2 + len..];
+    }
+    if !rest.is_empty() {
+        return Err(NetError::DecodeError);
+    }
+
+    CapabilitySet::from_parts(bits, names)
+}
+
 pub fn encode_get_blocks(p: &GetBlocksPayload) -> Vec<u8> {
     let mut v = Vec::new();
     for id in &p.ids {
@@ -141,14 +499,423 @@ pub fn decode_get_blocks(b: &[u8]) -> NetResult<GetBlocksPayload> {
     Ok(GetBlocksPayload { ids })
 }
 
+/// Part of a ranged GetBlocks request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSelector {
+    Id(u64),
+    /// Half-open `[start, end)`; `start < end`.
+    Range { start: u64, end: u64 },
+}
+
+/// GetBlocks request mixing single ids and ranges, with an optional byte
+/// budget for the reply. Sent as such when both peers advertise
+/// [`Capability::BLOCK_RANGES`], expanded to the flat id list otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GetBlockRanges {
+    pub selectors: Vec<BlockSelector>,
+    /// The responder stops adding blocks once the reply would exceed this
+    /// many bytes of block data; unanswered ids may be requested again.
+    pub max_bytes: Option<u64>,
+}
+
+impl GetBlockRanges {
+    /// Most ids a request may expand to: as many as a flat GetBlocks
+    /// payload holds under [`FrameLimits::default`].
+    pub const MAX_IDS: u64 = FrameLimits::DEFAULT_GET_BLOCKS_MAX as u64 / 8;
+
+    /// Compacts a flat id list: consecutive runs become ranges.
+    pub fn from_ids(ids: &[u64]) -> Self {
+        let mut selectors = Vec::new();
+        let mut i = 0;
+        while i < ids.len() {
+            let start = ids[i];
+            let mut j = i + 1;
+            while j < ids.len() && start.checked_add((j - i) as u64) == Some(ids[j]) {
+                j += 1;
+            }
+            if j - i == 1 {
+                selectors.push(BlockSelector::Id(start));
+            } else {
+                selectors.push(BlockSelector::Range { start, end: start + (j - i) as u64 });
+            }
+            i = j;
+        }
+        GetBlockRanges { selectors, max_bytes: None }
+    }
+
+    /// All requested ids, in request order. Fails with
+    /// [`NetError::FrameTooLarge`] if there are more than
+    /// [`GetBlockRanges::MAX_IDS`]: ranges come from the peer and must not
+    /// expand without bound.
+    pub fn ids(&self) -> NetResult<impl Iterator<Item = u64> + '_> {
+        let count = self.id_count();
+        if count > Self::MAX_IDS {
+            return Err(NetError::FrameTooLarge {
+                kind: frame_kind_byte(&FrameKind::GetBlocks),
+                length: count.saturating_mul(8).min(u32::MAX as u64) as u32,
+                max: FrameLimits::DEFAULT_GET_BLOCKS_MAX,
+            });
+        }
+        // take() вместо id..id + 1, чтобы u64::MAX не переполнялся
+        Ok(self.selectors.iter().flat_map(|s| match *s {
+            BlockSelector::Id(id) => (id..=u64::MAX).take(1),
+            BlockSelector::Range { start, end } => {
+                (start..=u64::MAX).take(end.saturating_sub(start) as usize)
+            }
+        }))
+    }
+
+    /// Number of requested ids (saturating).
+    pub fn id_count(&self) -> u64 {
+        self.selectors.iter().fold(0u64, |n, s| match *s {
+            BlockSelector::Id(_) => n.saturating_add(1),
+            BlockSelector::Range { start, end } => n.saturating_add(end.saturating_sub(start)),
+        })
+    }
+}
+
+const SELECT_ID: u8 = 0;
+const SELECT_RANGE: u8 = 1;
+
+/// Ranged GetBlocks: u8 has-budget, [u64 max bytes], u32 selector count,
+/// then per selector u8 tag and u64 id, or u64 start + u64 end.
+pub fn encode_get_block_ranges(p: &GetBlockRanges) -> Vec<u8> {
+    let mut v = Vec::new();
+    match p.max_bytes {
+        Some(max) => {
+            v.push(1);
+            v.extend_from_slice(&encode_u64(max));
+        }
+        None => v.push(0),
+    }
+    v.extend_from_slice(&encode_u32(p.selectors.len() as u32));
+    for sel in &p.selectors {
+        match *sel {
+            BlockSelector::Id(id) => {
+                v.push(SELECT_ID);
+                v.extend_from_slice(&encode_u64(id));
+            }
+            BlockSelector::Range { start, end } => {
+                v.push(SELECT_RANGE);
+                v.extend_from_slice(&encode_u64(start));
+                v.extend_from_slice(&encode_u64(end));
+            }
+        }
+    }
+    v
+}
+
+pub fn decode_get_block_ranges(b: &[u8]) -> NetResult<GetBlockRanges> {
+    let (max_bytes, rest) = match b.first() {
+        Some(0) => (None, &b[1..]),
+        Some(1) if b.len() >= 9 => (Some(decode_u64(&b[1..9])), &b[9..]),
+        _ => return Err(NetError::DecodeError),
+    };
+    if rest.len() < 4 {
+        return Err(NetError::DecodeError);
+    }
+    let count = decode_u32(&rest[0..4]) as usize;
+    let mut rest = &rest[4..];
+
+    let mut selectors = Vec::with_capacity(count.min(rest.len() / 9));
+    for _ in 0..count {
+        match rest.first() {
+            Some(&SELECT_ID) if rest.len() >= 9 => {
+                selectors.push(BlockSelector::Id(decode_u64(&rest[1..9])));
+                rest = &rest[9..];
+            }
+            Some(&SELECT_RANGE) if rest.len() >= 17 => {
+                let start = decode_u64(&rest[1..9]);
+                let end = decode_u64(&rest[9..17]);
+                if start >= end {
+                    return Err(NetError::DecodeError);
+                }
+                selectors.push(BlockSelector::Range { start, end });
+                rest = &rest[17..];
+            }
+            _ => return Err(NetError::DecodeError),
+        }
+    }
+    if !rest.is_empty() {
+        return Err(NetError::DecodeError);
+    }
+
+    Ok(GetBlockRanges { selectors, max_bytes })
+}
+
+/// Encodes a GetBlocks payload in the format negotiated in `caps`. Without
+/// [`Capability::BLOCK_RANGES`] ranges are expanded to the flat id list and
+/// the byte budget is dropped, which fails for more than
+/// [`GetBlockRanges::MAX_IDS`] ids.
+pub fn encode_get_blocks_for(caps: &CapabilitySet, p: &GetBlockRanges) -> NetResult<Vec<u8>> {
+    if caps.contains(Capability::BLOCK_RANGES) {
+        return Ok(encode_get_block_ranges(p));
+    }
+    Ok(encode_get_blocks(&GetBlocksPayload { ids: p.ids()?.collect() }))
+}
+
+/// Decodes a GetBlocks payload in the format negotiated in `caps`.
+pub fn decode_get_blocks_for(caps: &CapabilitySet, b: &[u8]) -> NetResult<GetBlockRanges> {
+    if caps.contains(Capability::BLOCK_RANGES) {
+        return decode_get_block_ranges(b);
+    }
+    let flat = decode_get_blocks(b)?;
+    Ok(GetBlockRanges {
+        selectors: flat.ids.into_iter().map(BlockSelector::Id).collect(),
+        max_bytes: None,
+    })
+}
+
 pub fn encode_push_blocks(p: &PushBlocksPayload) -> Vec<u8> {
     p.raw.clone()
 }
 
+pub fn encode_push_blocks_into(p: &PushBlocksPayload, out: &mut Vec<u8>) {
+    out.extend_from_slice(&p.raw);
+}
+
 pub fn decode_push_blocks(b: &[u8]) -> NetResult<PushBlocksPayload> {
     Ok(PushBlocksPayload { raw: b.to_vec() })
 }
 
+/// PushBlocks payload that shares the received frame's buffer instead of
+/// copying it like [`decode_push_blocks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedPushBlocks {
+    pub raw: Bytes,
+}
+
+pub fn decode_push_blocks_shared(b: &Bytes) -> NetResult<SharedPushBlocks> {
+    Ok(SharedPushBlocks { raw: b.clone() })
+}
+
+impl From<SharedPushBlocks> for PushBlocksPayload {
+    fn from(p: SharedPushBlocks) -> Self {
+        PushBlocksPayload { raw: p.raw.into() }
+    }
+}
+
+/// One answer inside a structured PushBlocks payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockEntry {
+    Present { id: u64, data: Vec<u8> },
+    /// The responder does not have this block.
+    Missing { id: u64 },
+}
+
+impl BlockEntry {
+    pub fn id(&self) -> u64 {
+        match self {
+            BlockEntry::Present { id, .. } | BlockEntry::Missing { id } => *id,
+        }
+    }
+}
+
+/// Structured PushBlocks payload, used once both peers advertise
+/// [`Capability::BLOCK_BATCH`]; otherwise PushBlocks carries the opaque
+/// [`PushBlocksPayload`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockBatch {
+    pub entries: Vec<BlockEntry>,
+}
+
+/// How a [`BlockBatch`] answers a GetBlocks request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockCoverage {
+    /// Requested and delivered.
+    pub satisfied: Vec<u64>,
+    /// Requested and explicitly reported missing.
+    pub missing: Vec<u64>,
+    /// Requested but not mentioned in the batch at all.
+    pub unanswered: Vec<u64>,
+    /// Present in the batch but never requested.
+    pub unexpected: Vec<u64>,
+}
+
+impl BlockCoverage {
+    /// Every requested id got an answer and nothing extra was sent.
+    pub fn is_complete(&self) -> bool {
+        self.unanswered.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+impl BlockBatch {
+    /// Matches the entries against the ids of a GetBlocks request.
+    pub fn coverage(&self, requested: &GetBlocksPayload) -> BlockCoverage {
+        let answered =
+            self.entries.iter().map(|e| (e.id(), matches!(e, BlockEntry::Present { .. })));
+        coverage(answered, requested)
+    }
+}
+
+/// [`BlockEntry`] whose data shares the received frame's buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharedBlockEntry {
+    Present { id: u64, data: Bytes },
+    /// The responder does not have this block.
+    Missing { id: u64 },
+}
+
+impl SharedBlockEntry {
+    pub fn id(&self) -> u64 {
+        match self {
+            SharedBlockEntry::Present { id, .. } | SharedBlockEntry::Missing { id } => *id,
+        }
+    }
+}
+
+impl From<SharedBlockEntry> for BlockEntry {
+    fn from(e: SharedBlockEntry) -> Self {
+        match e {
+            SharedBlockEntry::Present { id, data } => BlockEntry::Present { id, data: data.into() },
+            SharedBlockEntry::Missing { id } => BlockEntry::Missing { id },
+        }
+    }
+}
+
+/// Structured PushBlocks payload that shares the received frame's buffer
+/// instead of copying every block like [`decode_block_batch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SharedBlockBatch {
+    pub entries: Vec<SharedBlockEntry>,
+}
+
+impl SharedBlockBatch {
+    /// Matches the entries against the ids of a GetBlocks request.
+    pub fn coverage(&self, requested: &GetBlocksPayload) -> BlockCoverage {
+        let answered =
+            self.entries.iter().map(|e| (e.id(), matches!(e, SharedBlockEntry::Present { .. })));
+        coverage(answered, requested)
+    }
+}
+
+impl From<SharedBlockBatch> for BlockBatch {
+    fn from(b: SharedBlockBatch) -> Self {
+        BlockBatch { entries: b.entries.into_iter().map(BlockEntry::from).collect() }
+    }
+}
+
+/// Coverage of `requested` by batch entries given as (id, present).
+fn coverage(
+    answered: impl Iterator<Item = (u64, bool)>,
+    requested: &GetBlocksPayload,
+) -> BlockCoverage {
+    let answered: Vec<(u64, bool)> = answered.collect();
+    let by_id: HashMap<u64, bool> = answered.iter().copied().collect();
+    let wanted: HashSet<u64> = requested.ids.iter().copied().collect();
+
+    let mut cov = BlockCoverage::default();
+    for id in &requested.ids {
+        match by_id.get(id) {
+            Some(true)  => cov.satisfied.push(*id),
+            Some(false) => cov.missing.push(*id),
+            None        => cov.unanswered.push(*id),
+        }
+    }
+    cov.unexpected = answered.iter().map(|(id, _)| *id).filter(|id| !wanted.contains(id)).collect();
+    cov
+}
+
+const BLOCK_MISSING: u8 = 0;
+const BLOCK_PRESENT: u8 = 1;
+const BLOCK_HASH_LEN: usize = 32;
+
+/// Structured PushBlocks: u32 entry count, then per entry u8 tag and u64
+/// id; present entries add u32 length, 32-byte BLAKE3 hash and the data.
+pub fn encode_block_batch(p: &BlockBatch) -> Vec<u8> {
+    let mut v = Vec::new();
+    v.extend_from_slice(&encode_u32(p.entries.len() as u32));
+    for e in &p.entries {
+        match e {
+            BlockEntry::Present { id, data } => {
+                v.push(BLOCK_PRESENT);
+                v.extend_from_slice(&encode_u64(*id));
+                v.extend_from_slice(&encode_u32(data.len() as u32));
+                v.extend_from_slice(blake3::hash(data).as_bytes());
+                v.extend_from_slice(data);
+            }
+            BlockEntry::Missing { id } => {
+                v.push(BLOCK_MISSING);
+                v.extend_from_slice(&encode_u64(*id));
+            }
+        }
+    }
+    v
+}
+
+/// Decodes a structured PushBlocks payload, verifying every block's length
+/// and hash.
+pub fn decode_block_batch(b: &[u8]) -> NetResult<BlockBatch> {
+    let entries = parse_block_batch(b)?
+        .into_iter()
+        .map(|(id, data)| match data {
+            Some(r) => BlockEntry::Present { id, data: b[r].to_vec() },
+            None => BlockEntry::Missing { id },
+        })
+        .collect();
+    Ok(BlockBatch { entries })
+}
+
+/// Like [`decode_block_batch`], but every block is a slice of `b`.
+pub fn decode_block_batch_shared(b: &Bytes) -> NetResult<SharedBlockBatch> {
+    let entries = parse_block_batch(b)?
+        .into_iter()
+        .map(|(id, data)| match data {
+            Some(r) => SharedBlockEntry::Present { id, data: b.slice(r) },
+            None => SharedBlockEntry::Missing { id },
+        })
+        .collect();
+    Ok(SharedBlockBatch { entries })
+}
+
+/// Verified entries of a structured PushBlocks payload: the id and, for a
+/// present block, where its data lies in `b`.
+fn parse_block_batch(b: &[u8]) -> NetResult<Vec<(u64, Option<Range<usize>>)>> {
+    if b.len() < 4 {
+        return Err(NetError::DecodeError);
+    }
+    let count = decode_u32(&b[0..4]) as usize;
+    let mut at = 4;
+
+    // count приходит от пира — не резервируем больше, чем может влезть
+    let mut entries = Vec::with_capacity(count.min((b.len() - at) / 9));
+    for _ in 0..count {
+        if b.len() - at < 9 {
+            return Err(NetError::DecodeError);
+        }
+        let tag = b[at];
+        let id = decode_u64(&b[at + 1..at + 9]);
+        at += 9;
+
+        match tag {
+            BLOCK_MISSING => entries.push((id, None)),
+            BLOCK_PRESENT => {
+                if b.len() - at < 4 + BLOCK_HASH_LEN {
+                    return Err(NetError::DecodeError);
+                }
+                let len = decode_u32(&b[at..at + 4]) as usize;
+                let hash = &b[at + 4..at + 4 + BLOCK_HASH_LEN];
+                at += 4 + BLOCK_HASH_LEN;
+                if b.len() - at < len {
+                    return Err(NetError::DecodeError);
+                }
+                let data = at..at + len;
+                if blake3::hash(&b[data.clone()]).as_bytes() != hash {
+                    return Err(NetError::BlockHashMismatch { id });
+                }
+                entries.push((id, Some(data)));
+                at += len;
+            }
+            _ => return Err(NetError::DecodeError),
+        }
+    }
+    if at != b.len() {
+        return Err(NetError::DecodeError);
+    }
+
+    Ok(entries)
+}
+
 pub fn encode_get_object(p: &GetObjectPayload) -> Vec<u8> {
     encode_u64(p.id).to_vec()
 }
@@ -164,25 +931,1070 @@ pub fn encode_push_object(p: &PushObjectPayload) -> Vec<u8> {
     p.raw.clone()
 }
 
+pub fn encode_push_object_into(p: &PushObjectPayload, out: &mut Vec<u8>) {
+    out.extend_from_slice(&p.raw);
+}
+
 pub fn decode_push_object(b: &[u8]) -> NetResult<PushObjectPayload> {
     Ok(PushObjectPayload { raw: b.to_vec() })
 }
 
+/// PushObject payload that shares the received frame's buffer instead of
+/// copying it like [`decode_push_object`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedPushObject {
+    pub raw: Bytes,
+}
+
+pub fn decode_push_object_shared(b: &Bytes) -> NetResult<SharedPushObject> {
+    Ok(SharedPushObject { raw: b.clone() })
+}
+
+impl From<SharedPushObject> for PushObjectPayload {
+    fn from(p: SharedPushObject) -> Self {
+        PushObjectPayload { raw: p.raw.into() }
+    }
+}
+
+/// Ping: u64 nonce, u64 sender timestamp (µs since the Unix epoch).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PingPayload {
+    pub nonce: u64,
+    pub sent_at_us: u64,
+}
+
+/// Pong: echoes the Ping and adds the responder's receive timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PongPayload {
+    pub nonce: u64,
+    pub ping_sent_at_us: u64,
+    pub received_at_us: u64,
+}
+
+impl PongPayload {
+    /// Answer to `ping`, received at `received_at_us` by the responder's clock.
+    pub fn answer(ping: &PingPayload, received_at_us: u64) -> Self {
+        PongPayload {
+            nonce: ping.nonce,
+            ping_sent_at_us: ping.sent_at_us,
+            received_at_us,
+        }
+    }
+}
+
+pub fn encode_ping(p: &PingPayload) -> Vec<u8> {
+    let mut v = Vec::with_capacity(16);
+    v.extend_from_slice(&encode_u64(p.nonce));
+    v.extend_from_slice(&encode_u64(p.sent_at_us));
+    v
+}
+
+pub fn decode_ping(b: &[u8]) -> NetResult<PingPayload> {
+    if b.len() != 16 {
+        return Err(NetError::DecodeError);
+    }
+    Ok(PingPayload {
+        nonce: decode_u64(&b[0..8]),
+        sent_at_us: decode_u64(&b[8..16]),
+    })
+}
+
+pub fn encode_pong(p: &PongPayload) -> Vec<u8> {
+    let mut v = Vec::with_capacity(24);
+    v.extend_from_slice(&encode_u64(p.nonce));
+    v.extend_from_slice(&encode_u64(p.ping_sent_at_us));
+    v.extend_from_slice(&encode_u64(p.received_at_us));
+    v
+}
+
+pub fn decode_pong(b: &[u8]) -> NetResult<PongPayload> {
+    if b.len() != 24 {
+        return Err(NetError::DecodeError);
+    }
+    Ok(PongPayload {
+        nonce: decode_u64(&b[0..8]),
+        ping_sent_at_us: decode_u64(&b[8..16]),
+        received_at_us: decode_u64(&b[16..24]),
+    })
+}
+
+/// Payload of an Error frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorPayload {
+    pub code: ErrorCode,
+    /// Request id of the frame that failed, if it had one.
+    pub request: Option<u32>,
+    pub message: String,
+}
+
+/// Error: u16 code, u8 has-request, [u32 request id], u16 message length,
+/// UTF-8 message.
+pub fn encode_error(p: &ErrorPayload) -> Vec<u8> {
+    let mut v = Vec::new();
+    v.extend_from_slice(&encode_u16(p.code.to_u16()));
+    match p.request {
+        Some(id) => {
+            v.push(1);
+            v.extend_from_slice(&encode_u32(id));
+        }
+        None => v.push(0),
+    }
+    encode_message(&mut v, &p.message);
+    v
+}
+
+/// u16 length + UTF-8 text.
+fn encode_message(v: &mut Vec<u8>, message: &str) {
+    // сообщение — для людей; обрезаем по границе символа, чтобы влезло в u16
+    let mut end = message.len().min(u16::MAX as usize);
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    v.extend_from_slice(&encode_u16(end as u16));
+    v.extend_from_slice(&message.as_bytes()[..end]);
+}
+
+/// Inverse of [`encode_message`]; the message must fill `b` exactly.
+fn decode_message(b: &[u8]) -> NetResult<String> {
+    if b.len() < 2 || b.len() != 2 + decode_u16(&b[0..2]) as usize {
+        return Err(NetError::DecodeError);
+    }
+    let message = std::str::from_utf8(&b[2..]).map_err(|_| NetError::DecodeError)?;
+    Ok(message.to_string())
+}
+
+pub fn decode_error(b: &[u8]) -> NetResult<ErrorPayload> {
+    if b.len() < 3 {
+        return Err(NetError::DecodeError);
+    }
+    let code = ErrorCode::from_u16(decode_u16(&b[0..2]));
+    let (request, rest) = match b[2] {
+        0 => (None, &b[3..]),
+        1 if b.len() >= 7 => (Some(decode_u32(&b[3..7])), &b[7..]),
+        _ => return Err(NetError::DecodeError),
+    };
+    let message = decode_message(rest)?;
+
+    Ok(ErrorPayload { code, request, message })
+}
+
+impl From<ErrorPayload> for NetError {
+    fn from(p: ErrorPayload) -> Self {
+        NetError::Remote { code: p.code, message: p.message }
+    }
+}
+
+/// Why the peer is ending the session.
+///
+/// Codes are part of the wire format: never renumber, only append.
+/// Codes unknown to this build are preserved as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GoodbyeReason {
+    /// Node is shutting down or restarting.
+    Shutdown,
+    /// Node is draining connections; reconnect elsewhere or later.
+    Drain,
+    /// The receiver broke the protocol.
+    ProtocolViolation,
+    /// Connection was idle for too long.
+    Idle,
+    /// Node has too many connections.
+    Overloaded,
+    Other(u16),
+}
+
+impl GoodbyeReason {
+    pub fn to_u16(self) -> u16 {
+        match self {
+            GoodbyeReason::Shutdown          => 1,
+            GoodbyeReason::Drain             => 2,
+            GoodbyeReason::ProtocolViolation => 3,
+            GoodbyeReason::Idle              => 4,
+            GoodbyeReason::Overloaded        => 5,
+            GoodbyeReason::Other(c)          => c,
+        }
+    }
+
+    pub fn from_u16(c: u16) -> Self {
+        match c {
+            1 => GoodbyeReason::Shutdown,
+            2 => GoodbyeReason::Drain,
+            3 => GoodbyeReason::ProtocolViolation,
+            4 => GoodbyeReason::Idle,
+            5 => GoodbyeReason::Overloaded,
+            c => GoodbyeReason::Other(c),
+        }
+    }
+}
+
+/// Payload of a Goodbye frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoodbyePayload {
+    pub reason: GoodbyeReason,
+    /// Hint: do not reconnect before this much time has passed.
+    pub reconnect_after: Option<Duration>,
+    pub message: String,
+}
+
+impl GoodbyePayload {
+    pub fn new(reason: GoodbyeReason) -> Self {
+        GoodbyePayload { reason, reconnect_after: None, message: String::new() }
+    }
+
+    pub fn with_reconnect_after(mut self, d: Duration) -> Self {
+        self.reconnect_after = Some(d);
+        self
+    }
+
+    pub fn with_message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message = message.into();
+        self
+    }
+}
+
+/// Goodbye: u16 reason, u8 has-hint, [u32 reconnect-after ms], u16 message
+/// length, UTF-8 message.
+pub fn encode_goodbye(p: &GoodbyePayload) -> Vec<u8> {
+    let mut v = Vec::new();
+    v.extend_from_slice(&encode_u16(p.reason.to_u16()));
+    match p.reconnect_after {
+        Some(d) => {
+            v.push(1);
+            v.extend_from_slice(&encode_u32(d.as_millis().min(u32::MAX as u128) as u32));
+        }
+        None => v.push(0),
+    }
+    encode_message(&mut v, &p.message);
+    v
+}
+
+pub fn decode_goodbye(b: &[u8]) -> NetResult<GoodbyePayload> {
+    if b.len() < 3 {
+        return Err(NetError::DecodeError);
+    }
+    let reason = GoodbyeReason::from_u16(decode_u16(&b[0..2]));
+    let (reconnect_after, rest) = match b[2] {
+        0 => (None, &b[3..]),
+        1 if b.len() >= 7 => {
+            let ms = decode_u32(&b[3..7]);
+            (Some(Duration::from_millis(ms as u64)), &b[7..])
+        }
+        _ => return Err(NetError::DecodeError),
+    };
+    let message = decode_message(rest)?;
+
+    Ok(GoodbyePayload { reason, reconnect_after, message })
+}
+
+/// Authentication exchange an Auth frame belongs to.
+///
+/// Codes are part of the wire format: never renumber, only append.
+/// Codes unknown to this build are preserved as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthMethod {
+    /// Ed25519 signature over both Hellos (`crate::identity`).
+    Signature,
+    /// Nonce the peer has to answer with a cluster-secret MAC
+    /// (`crate::cluster_auth`).
+    ClusterChallenge,
+    /// HMAC-SHA256 answer to the peer's `ClusterChallenge`.
+    ClusterResponse,
+    Other(u8),
+}
+
+impl AuthMethod {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            AuthMethod::Signature        => 1,
+            AuthMethod::ClusterChallenge => 2,
+            AuthMethod::ClusterResponse  => 3,
+            AuthMethod::Other(c)         => c,
+        }
+    }
+
+    pub fn from_u8(c: u8) -> Self {
+        match c {
+            1 => AuthMethod::Signature,
+            2 => AuthMethod::ClusterChallenge,
+            3 => AuthMethod::ClusterResponse,
+            c => AuthMethod::Other(c),
+        }
+    }
+}
+
+/// Payload of an Auth frame; `data` is interpreted by the method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthPayload {
+    pub method: AuthMethod,
+    pub data: Vec<u8>,
+}
+
+/// Auth: u8 method, then method-specific bytes up to the end of the frame.
+pub fn encode_auth(p: &AuthPayload) -> Vec<u8> {
+    let mut v = Vec::with_capacity(1 + p.data.len());
+    v.push(p.method.to_u8());
+    v.extend_from_slice(&p.data);
+    v
+}
+
+pub fn decode_auth(b: &[u8]) -> NetResult<AuthPayload> {
+    let (&method, data) = b.split_first().ok_or(NetError::DecodeError)?;
+    Ok(AuthPayload { method: AuthMethod::from_u8(method), data: data.to_vec() })
+}
+
 /// -----------------------------
 /// Sending / Receiving Frames
 /// -----------------------------
+pub fn make_frame(kind: FrameKind, payload: Vec<u8>) -> Frame {
+    Frame::new(kind, payload)
+}
+
 pub fn send_frame<T: Transport>(t: &mut T, frame: &Frame) -> NetResult<()> {
-    let encoded = encode_frame(frame);
-    t.send(&encoded)
+    send_frame_with(t, frame, &FrameOptions::default())
+}
+
+pub fn send_frame_with<T: Transport>(t: &mut T, frame: &Frame, opts: &FrameOptions) -> NetResult<()> {
+    send_wire(t, &frame.header, &frame.payload, opts)
+}
+
+fn send_wire<T: Transport>(
+    t: &mut T,
+    header: &FrameHeader,
+    payload: &[u8],
+    opts: &FrameOptions,
+) -> NetResult<()> {
+    let compressed = opts.compress(payload);
+    let payload = compressed.as_deref().unwrap_or(payload);
+    let parts = WireParts::new(header, payload, compressed.is_some(), opts)?;
+    t.send_vectored(&[parts.prefix(), payload, parts.trailer()])
+}
+
+/// Sends a frame whose payload is borrowed, e.g. the `raw` bytes of a
+/// `PushObjectPayload`, without building a [`Frame`] or copying the payload
+/// (unless `opts` compress it). `header.length` is taken from `payload`;
+/// a payload of 4 GiB or more fails with [`NetError::FrameTooLarge`].
+pub fn send_frame_vectored<T: Transport>(
+    t: &mut T,
+    header: &FrameHeader,
+    payload: &[u8],
+    opts: &FrameOptions,
+) -> NetResult<()> {
+    let length = u32::try_from(payload.len()).map_err(|_| payload_too_large(&header.kind))?;
+    let header = FrameHeader { length, ..header.clone() };
+    send_wire(t, &header, payload, opts)
 }
 
+/// Receives one frame, enforcing [`FrameLimits::default`].
 pub fn recv_frame<T: Transport>(t: &mut T) -> NetResult<Frame> {
-    // читаем заголовок (6 байт)
-    let hdr_bytes = t.recv_exact(6)?;
-    let header = decode_frame_header(&hdr_bytes)?;
+    recv_frame_with_limits(t, &FrameLimits::default())
+}
+
+pub fn recv_frame_with_limits<T: Transport>(t: &mut T, limits: &FrameLimits) -> NetResult<Frame> {
+    loop {
+        // читаем заголовок (6 байт)
+        let hdr_bytes = t.recv_exact(6)?;
+        let header = decode_frame_header(&hdr_bytes)?;
+        limits.check(&header)?;
+
+        // читаем payload (и контрольную сумму, если есть)
+        let body = Bytes::from(t.recv_exact(body_len(&header))?);
+        if limits.is_unknown(&header) {
+            continue;
+        }
+
+        return finish_frame(header, body, limits);
+    }
+}
+
+/// What the peer sent: a regular frame, or the end of the session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incoming {
+    Frame(Frame),
+    /// The peer said Goodbye; it will send nothing more and close the
+    /// connection.
+    Goodbye(GoodbyePayload),
+}
+
+impl Incoming {
+    /// Classifies an already received frame (e.g. from [`FrameDecoder`]).
+    pub fn from_frame(f: Frame) -> NetResult<Self> {
+        if f.header.kind == FrameKind::Goodbye {
+            return Ok(Incoming::Goodbye(decode_goodbye(&f.payload)?));
+        }
+        Ok(Incoming::Frame(f))
+    }
+}
+
+/// Like [`recv_frame`], but reports a Goodbye as [`Incoming::Goodbye`]
+/// instead of handing back the raw frame.
+pub fn recv_incoming<T: Transport>(t: &mut T) -> NetResult<Incoming> {
+    recv_incoming_with_limits(t, &FrameLimits::default())
+}
+
+pub fn recv_incoming_with_limits<T: Transport>(t: &mut T, limits: &FrameLimits) -> NetResult<Incoming> {
+    Incoming::from_frame(recv_frame_with_limits(t, limits)?)
+}
+
+/// Tells the peer the session is over. The caller closes the transport
+/// afterwards.
+pub fn send_goodbye<T: Transport>(t: &mut T, p: &GoodbyePayload) -> NetResult<()> {
+    send_frame(t, &make_frame(FrameKind::Goodbye, encode_goodbye(p)))
+}
+
+/// Per-connection receive buffer for [`recv_frame_pooled`].
+///
+/// Frame bodies are read straight into one large allocation and handed out
+/// as [`Bytes`] slices of it. Once every frame taken from the buffer has
+/// been dropped, its memory is reused for the next frames; while some are
+/// still held, a new block is allocated and the old one lives as long as
+/// they do.
+#[derive(Debug)]
+pub struct RecvBuffer {
+    buf: BytesMut,
+    block_size: usize,
+}
+
+impl RecvBuffer {
+    pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+    pub fn new() -> Self {
+        Self::with_block_size(Self::DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Smallest allocation made at a time; bodies larger than this get an
+    /// allocation of their own size.
+    pub fn with_block_size(block_size: usize) -> Self {
+        RecvBuffer { buf: BytesMut::with_capacity(block_size), block_size }
+    }
+
+    fn read<T: Transport>(&mut self, t: &mut T, len: usize) -> NetResult<Bytes> {
+        if self.buf.capacity() < len {
+            self.buf.reserve(len.max(self.block_size));
+        }
+        self.buf.resize(len, 0);
+        t.recv_exact_into(&mut self.buf)?;
+        Ok(self.buf.split().freeze())
+    }
+}
+
+impl Default for RecvBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Like [`recv_frame_with_limits`], but the payload is a slice of `buf`
+/// instead of a fresh allocation.
+pub fn recv_frame_pooled<T: Transport>(
+    t: &mut T,
+    buf: &mut RecvBuffer,
+    limits: &FrameLimits,
+) -> NetResult<Frame> {
+    loop {
+        let mut hdr_bytes = [0u8; 6];
+        t.recv_exact_into(&mut hdr_bytes)?;
+        let header = decode_frame_header(&hdr_bytes)?;
+        limits.check(&header)?;
+
+        let body = buf.read(t, body_len(&header))?;
+        if limits.is_unknown(&header) {
+            continue;
+        }
+
+        return finish_frame(header, body, limits);
+    }
+}
+
+/// Error frame answering `request`: echoes its request id both in the header
+/// (for [`RpcClient`] routing) and in the payload.
+pub fn make_error_response(request: &Frame, code: ErrorCode, message: &str) -> Frame {
+    let payload = ErrorPayload {
+        code,
+        request: request.header.request_id,
+        message: message.to_string(),
+    };
+    make_response(request, FrameKind::Error, encode_error(&payload))
+}
+
+/// Turns a received Error frame into [`NetError::Remote`]; any other frame
+/// is passed through.
+pub fn check_remote_error(f: Frame) -> NetResult<Frame> {
+    if f.header.kind == FrameKind::Error {
+        return Err(decode_error(&f.payload)?.into());
+    }
+    Ok(f)
+}
+
+/// -----------------------------
+/// Handshake (Hello exchange)
+/// -----------------------------
+/// Result of a successful [`handshake`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    /// Node id the peer announced in its Hello. Authenticated only if
+    /// [`Session::peer_public_key`] is set.
+    pub peer_node: u64,
+    major: u16,
+    minor: u16,
+    pub(crate) peer_key: Option<[u8; 32]>,
+}
+
+impl Session {
+    /// Version both sides speak: the common major and the lower minor.
+    pub fn version(&self) -> ProtocolVersion {
+        ProtocolVersion { major: self.major, minor: self.minor }
+    }
+
+    /// Public key the peer proved to own in a signed Hello
+    /// (`crate::identity::signed_handshake`); `None` after a plain
+    /// [`handshake`].
+    pub fn peer_public_key(&self) -> Option<&[u8; 32]> {
+        self.peer_key.as_ref()
+    }
+}
+
+/// Exchanges Hello frames and checks version compatibility.
+///
+/// Majors must be equal; the session runs at the lower of the two minors.
+/// On a major mismatch the call fails with [`NetError::IncompatibleVersion`];
+/// with `notify_peer` an Error frame (`UnsupportedVersion`) is sent first so
+/// the peer learns why the connection is about to close.
+pub fn handshake<T: Transport>(t: &mut T, local: &HelloPayload, notify_peer: bool) -> NetResult<Session> {
+    send_frame(t, &make_frame(FrameKind::Hello, encode_hello(local)))?;
+    let frame = recv_hello(t)?;
+    accept_hello(t, &frame, local, notify_peer)
+}
+
+/// Receives the peer's Hello (or the Error it sent instead).
+pub(crate) fn recv_hello<T: Transport>(t: &mut T) -> NetResult<Frame> {
+    let frame = check_remote_error(recv_frame(t)?)?;
+    if frame.header.kind != FrameKind::Hello {
+        return Err(NetError::InvalidFrame);
+    }
+    Ok(frame)
+}
+
+/// Fails a handshake step with `err`. With `notify_peer` the peer is first
+/// sent an Error frame (`code`, `msg`) answering `frame`.
+pub(crate) fn refuse<T: Transport>(
+    t: &mut T,
+    frame: &Frame,
+    code: ErrorCode,
+    msg: &str,
+    err: NetError,
+    notify_peer: bool,
+) -> NetError {
+    if notify_peer {
+        // соединение всё равно закрывается — ошибку отправки не поднимаем
+        let _ = send_frame(t, &make_error_response(frame, code, msg));
+    }
+    err
+}
+
+/// Fresh random bytes for handshake nonces and keys.
+#[cfg(any(feature = "identity", feature = "cluster-auth"))]
+pub(crate) fn random_bytes<const N: usize>() -> NetResult<[u8; N]> {
+    let mut b = [0u8; N];
+    getrandom::getrandom(&mut b).map_err(|_| NetError::Io(std::io::ErrorKind::Other))?;
+    Ok(b)
+}
+
+/// Version check of [`handshake`], for the peer's Hello `frame`.
+pub(crate) fn accept_hello<T: Transport>(
+    t: &mut T,
+    frame: &Frame,
+    local: &HelloPayload,
+    notify_peer: bool,
+) -> NetResult<Session> {
+    let remote = decode_hello(&frame.payload)?;
+
+    if remote.version.major != local.version.major {
+        let msg = format!(
+            "protocol {}.x is not supported, this node speaks {}.{}",
+            remote.version.major, local.version.major, local.version.minor,
+        );
+        let err = NetError::IncompatibleVersion {
+            local: local.version.major,
+            remote: remote.version.major,
+        };
+        return Err(refuse(t, frame, ErrorCode::UnsupportedVersion, &msg, err, notify_peer));
+    }
+
+    Ok(Session {
+        peer_node: remote.node,
+        major: local.version.major,
+        minor: local.version.minor.min(remote.version.minor),
+        peer_key: None,
+    })
+}
+
+/// -----------------------------
+/// Capability exchange
+/// -----------------------------
+/// Sends our Caps frame, waits for the peer's and returns the negotiated
+/// set. Both sides call this right after Hello; since each sends before it
+/// receives, the exchange cannot deadlock.
+pub fn exchange_caps<T: Transport>(t: &mut T, local: &CapabilitySet) -> NetResult<CapabilitySet> {
+    send_frame(t, &make_frame(FrameKind::Caps, encode_caps(local)))?;
+
+    let frame = check_remote_error(recv_frame(t)?)?;
+    if !matches!(frame.header.kind, FrameKind::Caps) {
+        return Err(NetError::InvalidFrame);
+    }
+    let remote = decode_caps(&frame.payload)?;
+
+    Ok(negotiate(local, &remote))
+}
+
+/// -----------------------------
+/// Pipelined RPC (request ids)
+/// -----------------------------
+/// Builds the response to `request`, echoing its request id (if any) so the
+/// caller's [`RpcClient`] can route it.
+pub fn make_response(request: &Frame, kind: FrameKind, payload: Vec<u8>) -> Frame {
+    let mut f = Frame::new(kind, payload);
+    f.header.request_id = request.header.request_id;
+    f
+}
+
+/// Client side of request/response over a single [`Transport`].
+///
+/// Every request gets a fresh request id, so any number of them can be in
+/// flight at once; responses may arrive in any order and are handed to
+/// whoever waits for the matching id. Frames without a request id (pings,
+/// pushes initiated by the peer) are queued for [`RpcClient::take_unsolicited`].
+/// Once the peer says Goodbye, waiting for an unanswered request fails with
+/// [`NetError::ConnectionClosed`] and the reason is kept in
+/// [`RpcClient::goodbye`].
+///
+/// A response sent as an object stream (`crate::stream`) keeps its request
+/// in flight until the End frame; read it with [`RpcClient::wait_stream`].
+/// Its frames are never buffered: while it is open, waiting for any other
+/// request fails with [`NetError::StreamPending`] until it has been read.
+pub struct RpcClient<T: Transport> {
+    t: T,
+    opts: FrameOptions,
+    limits: FrameLimits,
+    buf: RecvBuffer,
+    next_id: u32,
+    in_flight: HashSet<u32>,
+    ready: HashMap<u32, VecDeque<Frame>>,
+    /// Request whose streamed response has started but whose End frame
+    /// has not been read yet.
+    stream: Option<u32>,
+    /// Cancelled requests whose responses are dropped as they arrive.
+    abandoned: HashSet<u32>,
+    unsolicited: VecDeque<Frame>,
+    goodbye: Option<GoodbyePayload>,
+}
+
+impl<T: Transport> RpcClient<T> {
+    pub fn new(t: T) -> Self {
+        RpcClient {
+            t,
+            opts: FrameOptions::default(),
+            limits: FrameLimits::default(),
+            buf: RecvBuffer::new(),
+            next_id: 1,
+            in_flight: HashSet::new(),
+            ready: HashMap::new(),
+            stream: None,
+            abandoned: HashSet::new(),
+            unsolicited: VecDeque::new(),
+            goodbye: None,
+        }
+    }
+
+    pub fn with_options(mut self, opts: FrameOptions) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: FrameLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn alloc_id(&mut self) -> u32 {
+        // 0 не используем, занятые id пропускаем после переполнения
+        loop {
+            let id = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1).max(1);
+            let taken = self.in_flight.contains(&id)
+                || self.ready.contains_key(&id)
+                || self.abandoned.contains(&id);
+            if !taken {
+                return id;
+            }
+        }
+    }
+
+    /// Sends a request without waiting for its response.
+    pub fn send_request(&mut self, kind: FrameKind, payload: Vec<u8>) -> NetResult<u32> {
+        let id = self.alloc_id();
+        let frame = Frame::new(kind, payload).with_request_id(id);
+        send_frame_with(&mut self.t, &frame, &self.opts)?;
+        self.in_flight.insert(id);
+        Ok(id)
+    }
+
+    /// Blocks until the response to request `id` arrives. Responses to
+    /// other requests read in the meantime are kept for their own callers.
+    /// An Error response is returned as [`NetError::Remote`].
+    ///
+    /// For a streamed response this returns its frames one per call. If
+    /// another request's stream starts first, fails with
+    /// [`NetError::StreamPending`]: read that stream, then wait again.
+    pub fn wait(&mut self, id: u32) -> NetResult<Frame> {
+        if !self.in_flight.contains(&id) && !self.ready.contains_key(&id) {
+            return Err(NetError::InvalidFrame);
+        }
+        loop {
+            if let Some(queue) = self.ready.get_mut(&id) {
+                let f = queue.pop_front().ok_or(NetError::InvalidFrame)?;
+                if queue.is_empty() {
+                    self.ready.remove(&id);
+                }
+                return check_remote_error(f);
+            }
+            // чужой поток не копим в памяти: сначала его читает wait_stream
+            if let Some(request_id) = self.stream.filter(|&s| s != id) {
+                return Err(NetError::StreamPending { request_id });
+            }
+            self.read_one()?;
+        }
+    }
+
+    /// Gives up on request `id`: a response already read is dropped, and
+    /// one still on its way is discarded when it arrives.
+    pub fn cancel(&mut self, id: u32) {
+        self.ready.remove(&id);
+        if self.in_flight.remove(&id) {
+            self.abandoned.insert(id);
+        }
+        if self.stream == Some(id) {
+            self.stream = None;
+        }
+    }
+
+    /// Receives the object stream answering request `id` into `w`.
+    pub fn wait_stream<W: Write>(&mut self, id: u32, w: &mut W) -> NetResult<StreamSummary> {
+        let first = self.wait(id)?;
+        self.finish_stream(id, &first, w)
+    }
+
+    /// Feeds `first` and the rest of request `id`'s stream to `w`.
+    fn finish_stream<W: Write>(
+        &mut self,
+        id: u32,
+        first: &Frame,
+        w: &mut W,
+    ) -> NetResult<StreamSummary> {
+        let mut rx = ObjectStreamReceiver::new(w);
+        let mut progress = rx.accept(first)?;
+        loop {
+            if let StreamProgress::Done(summary) = progress {
+                return Ok(summary);
+            }
+            progress = rx.accept(&self.wait(id)?)?;
+        }
+    }
+
+    /// Sends a request and waits for its response.
+    pub fn call(&mut self, kind: FrameKind, payload: Vec<u8>) -> NetResult<Frame> {
+        let id = self.send_request(kind, payload)?;
+        self.wait(id)
+    }
+
+    /// Fetches many objects with all requests in flight at once. Results
+    /// are returned in the order of `ids`; objects the peer streams are
+    /// read to the end and returned whole. If any of them fails, the rest
+    /// are cancelled.
+    pub fn get_objects(&mut self, ids: &[u64]) -> NetResult<Vec<PushObjectPayload>> {
+        let mut pending = Vec::with_capacity(ids.len());
+        for id in ids {
+            let payload = encode_get_object(&GetObjectPayload { id: *id });
+            match self.send_request(FrameKind::GetObject, payload) {
+                Ok(rid) => pending.push(rid),
+                Err(e) => return Err(self.cancel_all(&pending, e)),
+            }
+        }
+        self.collect_objects(&pending).map_err(|e| self.cancel_all(&pending, e))
+    }
+
+    fn cancel_all(&mut self, ids: &[u32], err: NetError) -> NetError {
+        for &id in ids {
+            self.cancel(id);
+        }
+        err
+    }
+
+    fn collect_objects(&mut self, pending: &[u32]) -> NetResult<Vec<PushObjectPayload>> {
+        let mut out: Vec<Option<PushObjectPayload>> = pending.iter().map(|_| None).collect();
+        for (i, &rid) in pending.iter().enumerate() {
+            while out[i].is_none() {
+                match self.wait(rid) {
+                    Ok(f) => out[i] = Some(self.object_response(rid, &f)?),
+                    // поток другого запроса из пачки пришёл раньше — читаем его сразу
+                    Err(NetError::StreamPending { request_id }) => {
+                        let j = pending
+                            .iter()
+                            .position(|&p| p == request_id)
+                            .ok_or(NetError::StreamPending { request_id })?;
+                        let f = self.wait(request_id)?;
+                        out[j] = Some(self.object_response(request_id, &f)?);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(out.into_iter().flatten().collect())
+    }
+
+    /// Object carried by `f`, the response to GetObject request `id`.
+    fn object_response(&mut self, id: u32, f: &Frame) -> NetResult<PushObjectPayload> {
+        if f.header.kind != FrameKind::PushObject {
+            return Err(NetError::InvalidFrame);
+        }
+        if f.header.flags & FLAG_STREAM == 0 {
+            return decode_push_object(&f.payload);
+        }
+        let mut raw = Vec::new();
+        self.finish_stream(id, f, &mut raw)?;
+        Ok(PushObjectPayload { raw })
+    }
+
+    /// Number of requests sent whose response has not been read yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn take_unsolicited(&mut self) -> Option<Frame> {
+        self.unsolicited.pop_front()
+    }
 
-    // читаем payload
-    let payload = t.recv_exact(header.length as usize)?;
+    /// Goodbye received from the peer, if the session has ended.
+    pub fn goodbye(&self) -> Option<&GoodbyePayload> {
+        self.goodbye.as_ref()
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.t
+    }
+
+    pub fn into_inner(self) -> T {
+        self.t
+    }
+
+    fn read_one(&mut self) -> NetResult<()> {
+        if self.goodbye.is_some() {
+            return Err(NetError::ConnectionClosed);
+        }
+        let f = recv_frame_pooled(&mut self.t, &mut self.buf, &self.limits)?;
+        let f = match Incoming::from_frame(f)? {
+            Incoming::Frame(f) => f,
+            Incoming::Goodbye(g) => {
+                self.goodbye = Some(g);
+                return Err(NetError::ConnectionClosed);
+            }
+        };
+        // поток отвечает многими кадрами: запрос закрывает только End
+        let streaming = f.header.kind == FrameKind::PushObject
+            && f.header.flags & (FLAG_STREAM | FLAG_MORE) == FLAG_STREAM | FLAG_MORE;
+        match f.header.request_id {
+            Some(rid) if self.in_flight.contains(&rid) => {
+                if streaming {
+                    self.stream = Some(rid);
+                } else {
+                    self.in_flight.remove(&rid);
+                    if self.stream == Some(rid) {
+                        self.stream = None;
+                    }
+                }
+                self.ready.entry(rid).or_default().push_back(f);
+            }
+            // ответ на отменённый запрос никому не нужен
+            Some(rid) if self.abandoned.contains(&rid) => {
+                if !streaming {
+                    self.abandoned.remove(&rid);
+                }
+            }
+            // ответ на запрос, которого мы не отправляли
+            Some(_) => return Err(NetError::InvalidFrame),
+            None => self.unsolicited.push_back(f),
+        }
+        Ok(())
+    }
+}
 
-    decode_frame(header, payload)
+/// -----------------------------
+/// Incremental decoding (без блокирующего Transport)
+/// -----------------------------
+/// Push-style frame decoder for event-loop / completion-based I/O.
+///
+/// Feed it whatever bytes the socket produced and pull out complete frames;
+/// partial headers and payloads are kept until the rest arrives. Validation
+/// is exactly that of [`recv_frame`]. After an error the stream is no longer
+/// frame-aligned and the connection should be dropped.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buf: BytesMut,
+    header: Option<FrameHeader>,
+    limits: FrameLimits,
+}
+
+impl FrameDecoder {
+    /// Decoder enforcing [`FrameLimits::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: FrameLimits) -> Self {
+        FrameDecoder { limits, ..Self::default() }
+    }
+
+    /// Appends received bytes to the internal buffer.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Returns the next complete frame, or `None` if more bytes are needed.
+    pub fn next_frame(&mut self) -> NetResult<Option<Frame>> {
+        loop {
+            let len = match &self.header {
+                Some(h) => body_len(h),
+                None => {
+                    if self.buf.len() < 6 {
+                        return Ok(None);
+                    }
+                    let h = decode_frame_header(&self.buf[..6])?;
+                    self.limits.check(&h)?;
+                    self.buf.advance(6);
+                    let len = body_len(&h);
+                    self.header = Some(h);
+                    len
+                }
+            };
+            if self.buf.len() < len {
+                return Ok(None);
+            }
+
+            let body = self.buf.split_to(len).freeze();
+            let header = self.header.take().ok_or(NetError::InvalidFrame)?;
+            if self.limits.is_unknown(&header) {
+                continue;
+            }
+            return finish_frame(header, body, &self.limits).map(Some);
+        }
+    }
+
+    /// Feeds `chunk` and returns every frame it completed.
+    pub fn decode(&mut self, chunk: &[u8]) -> NetResult<Vec<Frame>> {
+        self.feed(chunk);
+        let mut frames = Vec::new();
+        while let Some(f) = self.next_frame()? {
+            frames.push(f);
+        }
+        Ok(frames)
+    }
+
+    /// Bytes buffered but not yet returned as part of a frame
+    /// (a header already parsed is not counted).
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// True when no partial frame is pending, i.e. the peer may close here
+    /// without truncating anything.
+    pub fn is_idle(&self) -> bool {
+        self.header.is_none() && self.buf.is_empty()
+    }
+}
+
+/// -----------------------------
+/// Async Transport (feature = "async")
+/// -----------------------------
+#[cfg(feature = "async")]
+pub use self::async_transport::{
+    recv_frame_async, recv_frame_async_with_limits, send_frame_async, send_frame_async_with,
+    AsyncTransport,
+};
+
+#[cfg(feature = "async")]
+mod async_transport {
+    use std::future::Future;
+
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    use bytes::Bytes;
+
+    use super::{body_len, decode_frame_header, encode_frame_into, finish_frame, FrameLimits, FrameOptions};
+    use crate::error::NetResult;
+    use crate::frame::Frame;
+
+    /// Async counterpart of [`super::Transport`].
+    ///
+    /// Implemented for every `AsyncRead + AsyncWrite` stream (tokio
+    /// `TcpStream`, `UnixStream`, `DuplexStream`, ...).
+    pub trait AsyncTransport {
+        fn send(&mut self, data: &[u8]) -> impl Future<Output = NetResult<()>> + Send;
+        fn recv_exact(&mut self, len: usize) -> impl Future<Output = NetResult<Vec<u8>>> + Send;
+    }
+
+    impl<S> AsyncTransport for S
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        async fn send(&mut self, data: &[u8]) -> NetResult<()> {
+            self.write_all(data).await?;
+            self.flush().await?;
+            Ok(())
+        }
+
+        async fn recv_exact(&mut self, len: usize) -> NetResult<Vec<u8>> {
+            let mut buf = vec![0u8; len];
+            self.read_exact(&mut buf).await?;
+            Ok(buf)
+        }
+    }
+
+    /// Same wire format and validation as [`super::send_frame`].
+    pub async fn send_frame_async<T: AsyncTransport>(t: &mut T, frame: &Frame) -> NetResult<()> {
+        send_frame_async_with(t, frame, &FrameOptions::default()).await
+    }
+
+    /// Same wire format as [`super::send_frame_with`].
+    pub async fn send_frame_async_with<T: AsyncTransport>(
+        t: &mut T,
+        frame: &Frame,
+        opts: &FrameOptions,
+    ) -> NetResult<()> {
+        let mut encoded = Vec::new();
+        encode_frame_into(frame, opts, &mut encoded)?;
+        t.send(&encoded).await
+    }
+
+    /// Same wire format and validation as [`super::recv_frame`].
+    pub async fn recv_frame_async<T: AsyncTransport>(t: &mut T) -> NetResult<Frame> {
+        recv_frame_async_with_limits(t, &FrameLimits::default()).await
+    }
+
+    /// Same wire format and validation as [`super::recv_frame_with_limits`].
+    pub async fn recv_frame_async_with_limits<T: AsyncTransport>(
+        t: &mut T,
+        limits: &FrameLimits,
+    ) -> NetResult<Frame> {
+        loop {
+            let hdr_bytes = t.recv_exact(6).await?;
+            let header = decode_frame_header(&hdr_bytes)?;
+            limits.check(&header)?;
+
+            let body = Bytes::from(t.recv_exact(body_len(&header)).await?);
+            if limits.is_unknown(&header) {
+                continue;
+            }
+
+            return finish_frame(header, body, limits);
+        }
+    }
 }
@@ -0,0 +1,104 @@
+//! Application-defined frame kinds.
+//!
+//! Kind bytes `1..FIRST_EXTENSION_KIND` belong to the protocol itself;
+//! applications register their own kinds from [`FIRST_EXTENSION_KIND`] up
+//! in a [`FrameRegistry`] and hand it to the receive side through
+//! [`FrameLimits::with_registry`]. Registered kinds arrive as
+//! [`FrameKind::Extension`].
+//!
+//! A kind the receiver does not know (an unregistered extension, or a
+//! protocol kind newer than this build) is fatal unless the sender set
+//! [`FLAG_IGNORABLE`]; such frames are read and silently dropped. This lets
+//! new frame types roll out in a mixed-version cluster.
+//!
+//! [`FrameLimits::with_registry`]: crate::protocol::FrameLimits::with_registry
+//! [`FLAG_IGNORABLE`]: crate::protocol::FLAG_IGNORABLE
+
+use std::collections::BTreeMap;
+
+use crate::error::{NetError, NetResult};
+use crate::frame::{Frame, FrameKind};
+use crate::protocol::FLAG_IGNORABLE;
+
+/// Lowest kind byte available to applications.
+pub const FIRST_EXTENSION_KIND: u8 = 0x40;
+
+/// Typed codec of an application frame kind.
+pub trait ExtensionFrame: Sized {
+    /// Kind byte on the wire, at least [`FIRST_EXTENSION_KIND`].
+    const KIND: u8;
+    const NAME: &'static str;
+    /// Payload limit enforced by receivers that registered the kind.
+    const MAX_LEN: u32 = 64 * 1024;
+    /// Let receivers that do not know the kind skip it.
+    const IGNORABLE: bool = true;
+
+    fn encode_payload(&self) -> Vec<u8>;
+    fn decode_payload(b: &[u8]) -> NetResult<Self>;
+
+    fn to_frame(&self) -> Frame {
+        let mut f = Frame::new(FrameKind::Extension(Self::KIND), self.encode_payload());
+        if Self::IGNORABLE {
+            f.header.flags |= FLAG_IGNORABLE;
+        }
+        f
+    }
+
+    /// Decodes `f`, which must be of this kind.
+    fn from_frame(f: &Frame) -> NetResult<Self> {
+        if f.header.kind != FrameKind::Extension(Self::KIND) {
+            return Err(NetError::InvalidFrame);
+        }
+        Self::decode_payload(&f.payload)
+    }
+}
+
+/// A registered application kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionKind {
+    pub kind: u8,
+    pub name: &'static str,
+    pub max_len: u32,
+}
+
+/// Application frame kinds this node understands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameRegistry {
+    kinds: BTreeMap<u8, ExtensionKind>,
+}
+
+impl FrameRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<E: ExtensionFrame>(mut self) -> Self {
+        self.register::<E>();
+        self
+    }
+
+    pub fn register<E: ExtensionFrame>(&mut self) {
+        self.register_kind(ExtensionKind { kind: E::KIND, name: E::NAME, max_len: E::MAX_LEN });
+    }
+
+    /// Panics if the kind byte is reserved for the protocol or already taken:
+    /// both are programming errors that would otherwise surface only as
+    /// misrouted frames.
+    pub fn register_kind(&mut self, k: ExtensionKind) {
+        assert!(k.kind >= FIRST_EXTENSION_KIND, "frame kind {} is reserved", k.kind);
+        let prev = self.kinds.insert(k.kind, k);
+        assert!(prev.is_none(), "frame kind registered twice");
+    }
+
+    pub fn get(&self, kind: u8) -> Option<&ExtensionKind> {
+        self.kinds.get(&kind)
+    }
+
+    pub fn contains(&self, kind: u8) -> bool {
+        self.kinds.contains_key(&kind)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ExtensionKind> {
+        self.kinds.values()
+    }
+}
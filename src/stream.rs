@@ -0,0 +1,236 @@
+//! Chunked streaming of large objects over PushObject frames.
+//!
+//! A stream is a Start frame, any number of Chunk frames and an End frame,
+//! all of kind PushObject with [`FLAG_STREAM`] set; every frame but the End
+//! one also carries [`FLAG_MORE`]. Only one chunk is held in memory on
+//! either side, so object size is bounded by neither RAM nor
+//! `FrameHeader.length`.
+//!
+//! Payloads:
+//! - Start: u64 object id, u64 size hint (`u64::MAX` if unknown)
+//! - Chunk: raw object bytes
+//! - End:   u64 total length, 32-byte BLAKE3 hash of the whole object
+//!
+//! Use only when both peers advertise `Capability::OBJECT_STREAM`.
+
+use std::io::{Read, Write};
+
+use crate::error::{NetError, NetResult};
+use crate::frame::{Frame, FrameHeader, FrameKind};
+use crate::protocol::{
+    check_remote_error, recv_frame_with_limits, send_frame_vectored, send_frame_with, FrameLimits,
+    FrameOptions, Transport, FLAG_MORE, FLAG_STREAM,
+};
+
+const UNKNOWN_SIZE: u64 = u64::MAX;
+
+/// Sender-side settings for [`send_object_stream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Maximum payload of a Chunk frame. Must fit the receiver's
+    /// PushObject frame limit.
+    pub chunk_size: usize,
+    /// Request id put on every frame, e.g. when answering a GetObject; the
+    /// client reads such a response with `protocol::RpcClient::wait_stream`.
+    pub request_id: Option<u32>,
+    pub frame: FrameOptions,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            chunk_size: 1024 * 1024,
+            request_id: None,
+            frame: FrameOptions::default(),
+        }
+    }
+}
+
+/// What was transferred, as confirmed by the End frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamSummary {
+    pub id: u64,
+    pub length: u64,
+    pub hash: [u8; 32],
+}
+
+fn stream_frame(flags: u8, payload: Vec<u8>, request_id: Option<u32>) -> Frame {
+    let mut f = Frame::new(FrameKind::PushObject, payload);
+    f.header.flags = flags;
+    f.header.request_id = request_id;
+    f
+}
+
+/// Streams everything `r` yields as object `id`.
+///
+/// `size_hint` is passed to the receiver in the Start frame; it is not
+/// enforced.
+pub fn send_object_stream<T, R>(
+    t: &mut T,
+    id: u64,
+    size_hint: Option<u64>,
+    r: &mut R,
+    opts: &StreamOptions,
+) -> NetResult<StreamSummary>
+where
+    T: Transport,
+    R: Read,
+{
+    let mut start = Vec::with_capacity(16);
+    start.extend_from_slice(&id.to_be_bytes());
+    start.extend_from_slice(&size_hint.unwrap_or(UNKNOWN_SIZE).to_be_bytes());
+    send_frame_with(t, &stream_frame(FLAG_STREAM | FLAG_MORE, start, opts.request_id), &opts.frame)?;
+
+    // чанки уходят прямо из `buf`, без копии в Frame
+    let chunk = FrameHeader {
+        flags: FLAG_STREAM | FLAG_MORE,
+        request_id: opts.request_id,
+        ..FrameHeader::new(FrameKind::PushObject, 0)
+    };
+    let mut hasher = blake3::Hasher::new();
+    let mut length = 0u64;
+    let mut buf = vec![0u8; opts.chunk_size.max(1)];
+    loop {
+        let n = read_full(r, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        length += n as u64;
+        send_frame_vectored(t, &chunk, &buf[..n], &opts.frame)?;
+    }
+
+    let hash: [u8; 32] = *hasher.finalize().as_bytes();
+    let mut end = Vec::with_capacity(40);
+    end.extend_from_slice(&length.to_be_bytes());
+    end.extend_from_slice(&hash);
+    send_frame_with(t, &stream_frame(FLAG_STREAM, end, opts.request_id), &opts.frame)?;
+
+    Ok(StreamSummary { id, length, hash })
+}
+
+/// Fills `buf` as far as the reader allows; returns less only at EOF.
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> NetResult<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// Outcome of feeding one frame to an [`ObjectStreamReceiver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamProgress {
+    /// The stream continues; feed the next frame.
+    Continue,
+    /// End frame received and verified; the writer has been flushed.
+    Done(StreamSummary),
+}
+
+struct Active {
+    id: u64,
+    size_hint: Option<u64>,
+    request_id: Option<u32>,
+    hasher: blake3::Hasher,
+    length: u64,
+}
+
+/// Receiving side of an object stream, writing chunks to `W` as they come.
+///
+/// Frame-driven so it can be combined with other traffic on the same
+/// connection; [`recv_object_stream`] covers the simple case.
+pub struct ObjectStreamReceiver<W: Write> {
+    w: W,
+    active: Option<Active>,
+}
+
+impl<W: Write> ObjectStreamReceiver<W> {
+    pub fn new(w: W) -> Self {
+        ObjectStreamReceiver { w, active: None }
+    }
+
+    /// Whether `f` belongs to an object stream at all.
+    pub fn is_stream_frame(f: &Frame) -> bool {
+        f.header.kind == FrameKind::PushObject && f.header.flags & FLAG_STREAM != 0
+    }
+
+    /// Object id and size hint of the stream in progress, if any.
+    pub fn current(&self) -> Option<(u64, Option<u64>)> {
+        self.active.as_ref().map(|a| (a.id, a.size_hint))
+    }
+
+    pub fn accept(&mut self, f: &Frame) -> NetResult<StreamProgress> {
+        if !Self::is_stream_frame(f) {
+            return Err(NetError::InvalidFrame);
+        }
+        let more = f.header.flags & FLAG_MORE != 0;
+
+        let Some(a) = self.active.as_mut() else {
+            // первый кадр потока обязан быть Start
+            if !more || f.payload.len() != 16 {
+                return Err(NetError::InvalidFrame);
+            }
+            let id = u64::from_be_bytes(f.payload[0..8].try_into().unwrap());
+            let hint = u64::from_be_bytes(f.payload[8..16].try_into().unwrap());
+            self.active = Some(Active {
+                id,
+                size_hint: (hint != UNKNOWN_SIZE).then_some(hint),
+                request_id: f.header.request_id,
+                hasher: blake3::Hasher::new(),
+                length: 0,
+            });
+            return Ok(StreamProgress::Continue);
+        };
+
+        if f.header.request_id != a.request_id {
+            return Err(NetError::InvalidFrame);
+        }
+
+        if more {
+            self.w.write_all(&f.payload)?;
+            a.hasher.update(&f.payload);
+            a.length += f.payload.len() as u64;
+            return Ok(StreamProgress::Continue);
+        }
+
+        if f.payload.len() != 40 {
+            return Err(NetError::InvalidFrame);
+        }
+        let a = self.active.take().ok_or(NetError::InvalidFrame)?;
+        let length = u64::from_be_bytes(f.payload[0..8].try_into().unwrap());
+        let hash: [u8; 32] = f.payload[8..40].try_into().unwrap();
+        if length != a.length || *a.hasher.finalize().as_bytes() != hash {
+            return Err(NetError::ObjectIntegrity { id: a.id });
+        }
+        self.w.flush()?;
+
+        Ok(StreamProgress::Done(StreamSummary { id: a.id, length, hash }))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+/// Receives one complete object stream from `t` into `w`.
+///
+/// Any frame that is not part of the stream is a protocol error here; use
+/// [`ObjectStreamReceiver`] directly to interleave other traffic.
+pub fn recv_object_stream<T, W>(t: &mut T, w: &mut W, limits: &FrameLimits) -> NetResult<StreamSummary>
+where
+    T: Transport,
+    W: Write,
+{
+    let mut rx = ObjectStreamReceiver::new(w);
+    loop {
+        let f = check_remote_error(recv_frame_with_limits(t, limits)?)?;
+        if let StreamProgress::Done(summary) = rx.accept(&f)? {
+            return Ok(summary);
+        }
+    }
+}
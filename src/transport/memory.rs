@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use crate::error::{NetError, NetResult};
+use crate::protocol::Transport;
+
+/// Creates two connected in-memory endpoints.
+///
+/// Bytes sent on one endpoint are received on the other, in order. Nothing
+/// touches the OS, so protocol code built on `send_frame`/`recv_frame` can be
+/// tested deterministically.
+pub fn duplex() -> (MemoryTransport, MemoryTransport) {
+    let (a_tx, b_rx) = mpsc::channel();
+    let (b_tx, a_rx) = mpsc::channel();
+    (MemoryTransport::new(a_tx, a_rx), MemoryTransport::new(b_tx, b_rx))
+}
+
+/// One end of a [`duplex`] pair.
+///
+/// Besides [`Transport`] it implements `io::Read`/`io::Write`, and offers
+/// hooks to script short reads, inject errors and close the connection.
+pub struct MemoryTransport {
+    tx: Option<Sender<Vec<u8>>>,
+    rx: Receiver<Vec<u8>>,
+    pending: VecDeque<u8>,
+    short_reads: VecDeque<usize>,
+    send_faults: VecDeque<NetError>,
+    recv_faults: VecDeque<NetError>,
+    read_timeout: Option<Duration>,
+}
+
+impl MemoryTransport {
+    fn new(tx: Sender<Vec<u8>>, rx: Receiver<Vec<u8>>) -> Self {
+        MemoryTransport {
+            tx: Some(tx),
+            rx,
+            pending: VecDeque::new(),
+            short_reads: VecDeque::new(),
+            send_faults: VecDeque::new(),
+            recv_faults: VecDeque::new(),
+            read_timeout: None,
+        }
+    }
+
+    /// Closes the sending direction. The peer drains what was already sent
+    /// and then gets [`NetError::ConnectionClosed`]; our own sends fail too.
+    pub fn close(&mut self) {
+        self.tx = None;
+    }
+
+    /// Limits the size of the next reads: the n-th read from the underlying
+    /// channel delivers at most `sizes[n]` bytes. `recv_exact` keeps reading
+    /// until it has `len` bytes, so this exercises reassembly paths.
+    pub fn script_short_reads<I: IntoIterator<Item = usize>>(&mut self, sizes: I) {
+        self.short_reads.extend(sizes);
+    }
+
+    /// Makes the next `send` fail with `err` without delivering anything.
+    pub fn fail_next_send(&mut self, err: NetError) {
+        self.send_faults.push_back(err);
+    }
+
+    /// Makes the next `recv_exact` fail with `err` without consuming anything.
+    pub fn fail_next_recv(&mut self, err: NetError) {
+        self.recv_faults.push_back(err);
+    }
+
+    /// Reads block at most this long before failing with [`NetError::Timeout`].
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    /// Number of received bytes not consumed yet.
+    pub fn buffered(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn fill(&mut self) -> NetResult<()> {
+        while self.pending.is_empty() {
+            let chunk = match self.read_timeout {
+                Some(t) => self.rx.recv_timeout(t).map_err(|e| match e {
+                    RecvTimeoutError::Timeout      => NetError::Timeout,
+                    RecvTimeoutError::Disconnected => NetError::ConnectionClosed,
+                })?,
+                None => self.rx.recv().map_err(|_| NetError::ConnectionClosed)?,
+            };
+            self.pending.extend(chunk);
+        }
+        Ok(())
+    }
+
+    /// Одно "чтение из сокета": не больше `max` байт и не больше,
+    /// чем разрешает очередной шаг сценария.
+    fn read_some(&mut self, max: usize) -> NetResult<Vec<u8>> {
+        self.fill()?;
+        let mut n = max.min(self.pending.len());
+        if let Some(limit) = self.short_reads.pop_front() {
+            n = n.min(limit.max(1));
+        }
+        Ok(self.pending.drain(..n).collect())
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        if let Some(err) = self.send_faults.pop_front() {
+            return Err(err);
+        }
+        let tx = self.tx.as_ref().ok_or(NetError::ConnectionClosed)?;
+        tx.send(data.to_vec()).map_err(|_| NetError::ConnectionClosed)
+    }
+
+    fn recv_exact(&mut self, len: usize) -> NetResult<Vec<u8>> {
+        if let Some(err) = self.recv_faults.pop_front() {
+            return Err(err);
+        }
+        let mut buf = Vec::with_capacity(len);
+        while buf.len() < len {
+            let part = self.read_some(len - buf.len())?;
+            buf.extend_from_slice(&part);
+        }
+        Ok(buf)
+    }
+}
+
+fn to_io(e: NetError) -> io::Error {
+    match e {
+        NetError::Timeout          => io::ErrorKind::WouldBlock.into(),
+        NetError::ConnectionClosed => io::ErrorKind::BrokenPipe.into(),
+        NetError::Io(kind)         => kind.into(),
+        other                      => io::Error::other(other),
+    }
+}
+
+impl io::Read for MemoryTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.read_some(buf.len()) {
+            Ok(part) => {
+                buf[..part.len()].copy_from_slice(&part);
+                Ok(part.len())
+            }
+            // для io::Read закрытие пира — это EOF
+            Err(NetError::ConnectionClosed) => Ok(0),
+            Err(e) => Err(to_io(e)),
+        }
+    }
+}
+
+impl io::Write for MemoryTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf).map_err(to_io)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
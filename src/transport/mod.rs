@@ -1 +1,15 @@
-// TODO: QuarXNet transport implementations
+//! Concrete implementations of [`crate::protocol::Transport`].
+
+pub mod tcp;
+pub mod memory;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "noise")]
+pub mod noise;
+
+pub use tcp::{TcpConfig, TcpTransport, TcpTransportListener};
+pub use memory::{duplex, MemoryTransport};
+#[cfg(feature = "tls")]
+pub use tls::{PeerIdentity, TlsConfig, TlsIdentity, TlsTransport, TlsTransportListener};
+#[cfg(feature = "noise")]
+pub use noise::{NoiseKeypair, NoiseTransport};
@@ -0,0 +1,299 @@
+//! Noise handshake and encrypted framing (feature `noise`), for links where
+//! running a PKI is not worth it.
+//!
+//! Every node has a static X25519 key pair. Before Hello, the connecting
+//! side runs `Noise_XX` (neither side knows the other's key) or `Noise_IK`
+//! (the responder's key is known in advance, one round trip less); after
+//! that every byte on the wrapped transport travels in ChaCha20-Poly1305
+//! records. The peer is identified by its static public key:
+//! [`NoiseTransport::peer_node_id`] derives the only node id it may claim.
+//!
+//! Wire format:
+//! - initiator first sends one byte naming the pattern (0 = XX, 1 = IK);
+//! - handshake and transport messages are a u16 big-endian length followed
+//!   by that many bytes of Noise message.
+
+use bytes::{Buf, BytesMut};
+use snow::params::DHChoice;
+use snow::resolvers::{CryptoResolver, DefaultResolver};
+use snow::{Builder, HandshakeState, TransportState};
+
+use crate::error::{NetError, NetResult};
+use crate::protocol::Transport;
+
+pub use crate::node_key::node_id;
+
+const PARAMS_XX: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+const PARAMS_IK: &str = "Noise_IK_25519_ChaChaPoly_BLAKE2s";
+const PROLOGUE: &[u8] = b"quarxnet noise v1";
+
+const PATTERN_XX: u8 = 0;
+const PATTERN_IK: u8 = 1;
+
+const MAX_MESSAGE: usize = 65535;
+/// Poly1305 tag appended to every transport message.
+const TAG_LEN: usize = 16;
+const MAX_PLAINTEXT: usize = MAX_MESSAGE - TAG_LEN;
+/// Parts of a vectored send up to this size (frame headers, small
+/// payloads) share records; larger ones are encrypted straight from the
+/// caller's slice.
+const GATHER_MAX: usize = 4096;
+
+fn noise_error(e: snow::Error) -> NetError {
+    NetError::Noise(e.to_string())
+}
+
+fn builder(params: &'static str) -> Builder<'static> {
+    // строки параметров константные — разбор не может упасть
+    Builder::new(params.parse().expect("valid noise params"))
+}
+
+/// Static X25519 key pair of a node.
+#[derive(Clone)]
+pub struct NoiseKeypair {
+    private: [u8; 32],
+    public: [u8; 32],
+}
+
+impl NoiseKeypair {
+    pub fn generate() -> NetResult<Self> {
+        let kp = builder(PARAMS_XX).generate_keypair().map_err(noise_error)?;
+        Self::from_private_key(&kp.private)
+    }
+
+    /// Restores a key pair from a private key saved with
+    /// [`NoiseKeypair::private_key`]; the public key is derived from it.
+    pub fn from_private_key(private: &[u8]) -> NetResult<Self> {
+        let private: [u8; 32] = private
+            .try_into()
+            .map_err(|_| NetError::Noise("static keys must be 32 bytes".into()))?;
+        let mut dh = DefaultResolver
+            .resolve_dh(&DHChoice::Curve25519)
+            .ok_or_else(|| NetError::Noise("x25519 is not available".into()))?;
+        dh.set(&private);
+        let public = dh.pubkey().try_into().expect("x25519 public keys are 32 bytes");
+        Ok(NoiseKeypair { private, public })
+    }
+
+    /// Like [`NoiseKeypair::from_private_key`], but also checks `public`
+    /// against the private key: a mismatch would make [`NoiseKeypair::node_id`]
+    /// name a key the handshake never proves.
+    pub fn from_keys(private: &[u8], public: &[u8]) -> NetResult<Self> {
+        let kp = Self::from_private_key(private)?;
+        if kp.public[..] != *public {
+            return Err(NetError::Noise("public key does not match private key".into()));
+        }
+        Ok(kp)
+    }
+
+    pub fn private_key(&self) -> &[u8; 32] {
+        &self.private
+    }
+
+    pub fn public_key(&self) -> &[u8; 32] {
+        &self.public
+    }
+
+    pub fn node_id(&self) -> u64 {
+        node_id(&self.public)
+    }
+}
+
+impl std::fmt::Debug for NoiseKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoiseKeypair").field("public", &self.public).finish_non_exhaustive()
+    }
+}
+
+fn send_message<T: Transport>(t: &mut T, msg: &[u8]) -> NetResult<()> {
+    t.send_vectored(&[&(msg.len() as u16).to_be_bytes(), msg])
+}
+
+fn recv_message<T: Transport>(t: &mut T) -> NetResult<Vec<u8>> {
+    let len = t.recv_exact(2)?;
+    t.recv_exact(u16::from_be_bytes([len[0], len[1]]) as usize)
+}
+
+fn write_handshake<T: Transport>(t: &mut T, hs: &mut HandshakeState) -> NetResult<()> {
+    let mut buf = vec![0u8; MAX_MESSAGE];
+    let n = hs.write_message(&[], &mut buf).map_err(noise_error)?;
+    send_message(t, &buf[..n])
+}
+
+fn read_handshake<T: Transport>(t: &mut T, hs: &mut HandshakeState) -> NetResult<()> {
+    let msg = recv_message(t)?;
+    let mut buf = vec![0u8; MAX_MESSAGE];
+    hs.read_message(&msg, &mut buf).map_err(noise_error)?;
+    Ok(())
+}
+
+/// [`Transport`] wrapper that encrypts and authenticates everything sent
+/// over `T`. A record that fails authentication is reported as
+/// [`NetError::Noise`]; the connection must then be dropped.
+pub struct NoiseTransport<T: Transport> {
+    inner: T,
+    state: TransportState,
+    peer_key: [u8; 32],
+    plain: BytesMut,
+}
+
+impl<T: Transport> NoiseTransport<T> {
+    /// Initiator side of `Noise_XX`: learns the responder's key during the
+    /// handshake.
+    pub fn initiate_xx(mut t: T, keys: &NoiseKeypair) -> NetResult<Self> {
+        let mut hs = builder(PARAMS_XX)
+            .local_private_key(&keys.private)
+            .prologue(PROLOGUE)
+            .build_initiator()
+            .map_err(noise_error)?;
+        t.send(&[PATTERN_XX])?;
+        write_handshake(&mut t, &mut hs)?;
+        read_handshake(&mut t, &mut hs)?;
+        write_handshake(&mut t, &mut hs)?;
+        Self::finish(t, hs)
+    }
+
+    /// Initiator side of `Noise_IK`: the responder must hold the private
+    /// key for `remote`, otherwise the handshake fails.
+    pub fn initiate_ik(mut t: T, keys: &NoiseKeypair, remote: &[u8; 32]) -> NetResult<Self> {
+        let mut hs = builder(PARAMS_IK)
+            .local_private_key(&keys.private)
+            .remote_public_key(remote)
+            .prologue(PROLOGUE)
+            .build_initiator()
+            .map_err(noise_error)?;
+        t.send(&[PATTERN_IK])?;
+        write_handshake(&mut t, &mut hs)?;
+        read_handshake(&mut t, &mut hs)?;
+        Self::finish(t, hs)
+    }
+
+    /// Responder side; accepts either pattern.
+    pub fn respond(mut t: T, keys: &NoiseKeypair) -> NetResult<Self> {
+        let pattern = t.recv_exact(1)?[0];
+        let params = match pattern {
+            PATTERN_XX => PARAMS_XX,
+            PATTERN_IK => PARAMS_IK,
+            _ => return Err(NetError::Noise(format!("unknown handshake pattern {pattern}"))),
+        };
+        let mut hs = builder(params)
+            .local_private_key(&keys.private)
+            .prologue(PROLOGUE)
+            .build_responder()
+            .map_err(noise_error)?;
+        read_handshake(&mut t, &mut hs)?;
+        write_handshake(&mut t, &mut hs)?;
+        if pattern == PATTERN_XX {
+            read_handshake(&mut t, &mut hs)?;
+        }
+        Self::finish(t, hs)
+    }
+
+    fn finish(t: T, hs: HandshakeState) -> NetResult<Self> {
+        let peer_key: [u8; 32] = hs
+            .get_remote_static()
+            .and_then(|k| k.try_into().ok())
+            .ok_or_else(|| NetError::Noise("peer sent no static key".into()))?;
+        Ok(NoiseTransport {
+            inner: t,
+            state: hs.into_transport_mode().map_err(noise_error)?,
+            peer_key,
+            plain: BytesMut::new(),
+        })
+    }
+
+    /// Peer's static public key, authenticated by the handshake.
+    pub fn peer_static_key(&self) -> &[u8; 32] {
+        &self.peer_key
+    }
+
+    /// The node id the peer may use in its Hello.
+    pub fn peer_node_id(&self) -> u64 {
+        node_id(&self.peer_key)
+    }
+
+    /// Fails with [`NetError::PeerIdentityMismatch`] unless `node` (e.g.
+    /// from the peer's Hello) is the id bound to its static key.
+    pub fn check_node(&self, node: u64) -> NetResult<()> {
+        if node != self.peer_node_id() {
+            return Err(NetError::PeerIdentityMismatch { node });
+        }
+        Ok(())
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Encrypts `chunk` (at most `MAX_PLAINTEXT` bytes) into one record
+    /// appended to `out`.
+    fn seal(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> NetResult<()> {
+        let at = out.len();
+        out.resize(at + 2 + chunk.len() + TAG_LEN, 0);
+        let n = self.state.write_message(chunk, &mut out[at + 2..]).map_err(noise_error)?;
+        out[at..at + 2].copy_from_slice(&(n as u16).to_be_bytes());
+        out.truncate(at + 2 + n);
+        Ok(())
+    }
+
+    /// Decrypts records straight into `plain` until it holds `len` bytes.
+    fn fill(&mut self, len: usize) -> NetResult<()> {
+        while self.plain.len() < len {
+            let record = recv_message(&mut self.inner)?;
+            let at = self.plain.len();
+            self.plain.resize(at + record.len(), 0);
+            match self.state.read_message(&record, &mut self.plain[at..]) {
+                Ok(n) => self.plain.truncate(at + n),
+                Err(e) => {
+                    self.plain.truncate(at);
+                    return Err(noise_error(e));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for NoiseTransport<T> {
+    fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        self.send_vectored(&[data])
+    }
+
+    fn recv_exact(&mut self, len: usize) -> NetResult<Vec<u8>> {
+        self.fill(len)?;
+        Ok(self.plain.split_to(len).to_vec())
+    }
+
+    /// Records of up to 64 KiB, all handed to the inner transport in a
+    /// single `send`.
+    fn send_vectored(&mut self, parts: &[&[u8]]) -> NetResult<()> {
+        let total: usize = parts.iter().map(|p| p.len()).sum();
+        let mut out = Vec::with_capacity(total + (total / MAX_PLAINTEXT + parts.len()) * (2 + TAG_LEN));
+        let mut gathered = Vec::new();
+        for part in parts {
+            let flush = part.len() > GATHER_MAX || gathered.len() + part.len() > MAX_PLAINTEXT;
+            if flush && !gathered.is_empty() {
+                self.seal(&gathered, &mut out)?;
+                gathered.clear();
+            }
+            if part.len() > GATHER_MAX {
+                for chunk in part.chunks(MAX_PLAINTEXT) {
+                    self.seal(chunk, &mut out)?;
+                }
+            } else {
+                gathered.extend_from_slice(part);
+            }
+        }
+        if !gathered.is_empty() {
+            self.seal(&gathered, &mut out)?;
+        }
+        self.inner.send(&out)
+    }
+
+    fn recv_exact_into(&mut self, buf: &mut [u8]) -> NetResult<()> {
+        self.fill(buf.len())?;
+        buf.copy_from_slice(&self.plain[..buf.len()]);
+        self.plain.advance(buf.len());
+        Ok(())
+    }
+}
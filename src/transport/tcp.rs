@@ -0,0 +1,174 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::error::{NetError, NetResult};
+use crate::protocol::Transport;
+
+/// Socket options applied to every [`TcpTransport`].
+#[derive(Debug, Clone)]
+pub struct TcpConfig {
+    /// Timeout for a single blocking read; `None` blocks forever.
+    /// Expiry surfaces as [`NetError::Timeout`].
+    pub read_timeout: Option<Duration>,
+    /// Timeout for a single blocking write; `None` blocks forever.
+    pub write_timeout: Option<Duration>,
+    /// Timeout for establishing the connection in [`TcpTransport::connect`].
+    pub connect_timeout: Option<Duration>,
+    /// Disable Nagle's algorithm. Frames are small and latency-sensitive,
+    /// so this is on by default.
+    pub nodelay: bool,
+    /// Capacity of the read and write buffers.
+    pub buffer_size: usize,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        TcpConfig {
+            read_timeout: None,
+            write_timeout: None,
+            connect_timeout: None,
+            nodelay: true,
+            buffer_size: 64 * 1024,
+        }
+    }
+}
+
+/// Blocking [`Transport`] over a `TcpStream` with buffered reads and writes.
+///
+/// Every `send` is flushed before it returns, so a frame written by
+/// `send_frame` never sits in the buffer while the caller waits for a reply.
+pub struct TcpTransport {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl TcpTransport {
+    /// Connects to the first reachable address in `addr`.
+    pub fn connect<A: ToSocketAddrs>(addr: A, config: &TcpConfig) -> NetResult<Self> {
+        let mut last_err = NetError::Io(std::io::ErrorKind::AddrNotAvailable);
+
+        for sa in addr.to_socket_addrs()? {
+            let res = match config.connect_timeout {
+                Some(t) => TcpStream::connect_timeout(&sa, t),
+                None    => TcpStream::connect(sa),
+            };
+            match res {
+                Ok(stream) => return Self::from_stream(stream, config),
+                Err(e)     => last_err = e.into(),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    /// Wraps an already connected stream and applies `config` to it.
+    pub fn from_stream(stream: TcpStream, config: &TcpConfig) -> NetResult<Self> {
+        stream.set_read_timeout(config.read_timeout)?;
+        stream.set_write_timeout(config.write_timeout)?;
+        stream.set_nodelay(config.nodelay)?;
+
+        let write_half = stream.try_clone()?;
+        Ok(TcpTransport {
+            reader: BufReader::with_capacity(config.buffer_size, stream),
+            writer: BufWriter::with_capacity(config.buffer_size, write_half),
+        })
+    }
+
+    pub fn peer_addr(&self) -> NetResult<SocketAddr> {
+        Ok(self.reader.get_ref().peer_addr()?)
+    }
+
+    pub fn local_addr(&self) -> NetResult<SocketAddr> {
+        Ok(self.reader.get_ref().local_addr()?)
+    }
+
+    /// Changes the read timeout of an established connection.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> NetResult<()> {
+        Ok(self.reader.get_ref().set_read_timeout(timeout)?)
+    }
+
+    /// Changes the write timeout of an established connection.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> NetResult<()> {
+        Ok(self.writer.get_ref().set_write_timeout(timeout)?)
+    }
+
+    /// Unwraps the socket for a protocol layered on top (e.g. TLS).
+    /// Fails if the read buffer already holds bytes of the peer's.
+    pub fn into_stream(mut self) -> NetResult<TcpStream> {
+        self.writer.flush()?;
+        if !self.reader.buffer().is_empty() {
+            return Err(NetError::InvalidFrame);
+        }
+        Ok(self.reader.into_inner())
+    }
+
+    /// Flushes pending output and shuts down both directions of the socket.
+    ///
+    /// The peer's next `recv_exact` fails with [`NetError::ConnectionClosed`].
+    pub fn shutdown(&mut self) -> NetResult<()> {
+        self.writer.flush()?;
+        match self.reader.get_ref().shutdown(Shutdown::Both) {
+            // пир мог закрыть сокет раньше нас — это не ошибка
+            Err(e) if e.kind() == std::io::ErrorKind::NotConnected => Ok(()),
+            res => Ok(res?),
+        }
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        self.writer.write_all(data)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Parts larger than the write buffer go to the socket directly,
+    /// without being copied into it.
+    fn send_vectored(&mut self, parts: &[&[u8]]) -> NetResult<()> {
+        for part in parts {
+            self.writer.write_all(part)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Note: on [`NetError::Timeout`] the bytes read so far are dropped and
+    /// the stream is no longer frame-aligned; the connection should be closed.
+    fn recv_exact(&mut self, len: usize) -> NetResult<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn recv_exact_into(&mut self, buf: &mut [u8]) -> NetResult<()> {
+        self.reader.read_exact(buf)?;
+        Ok(())
+    }
+}
+
+/// Accepting side of [`TcpTransport`].
+pub struct TcpTransportListener {
+    inner: TcpListener,
+    config: TcpConfig,
+}
+
+impl TcpTransportListener {
+    /// Binds a listener; accepted connections get `config` applied.
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: TcpConfig) -> NetResult<Self> {
+        Ok(TcpTransportListener {
+            inner: TcpListener::bind(addr)?,
+            config,
+        })
+    }
+
+    /// Blocks until the next incoming connection.
+    pub fn accept(&self) -> NetResult<(TcpTransport, SocketAddr)> {
+        let (stream, peer) = self.inner.accept()?;
+        Ok((TcpTransport::from_stream(stream, &self.config)?, peer))
+    }
+
+    pub fn local_addr(&self) -> NetResult<SocketAddr> {
+        Ok(self.inner.local_addr()?)
+    }
+}
@@ -0,0 +1,347 @@
+//! TLS over [`TcpTransport`] (feature `tls`), with mutual authentication.
+//!
+//! Every node holds a certificate issued by the cluster CA for the DNS name
+//! [`node_name`]`(id)`. Both sides present their certificate and verify the
+//! peer's against the CA, so after the TLS handshake
+//! [`TlsTransport::peer_identity`] says which node ids the peer may claim;
+//! check it against `HelloPayload::node` with [`PeerIdentity::check_node`].
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+
+use super::tcp::{TcpConfig, TcpTransport, TcpTransportListener};
+use crate::error::{NetError, NetResult};
+use crate::protocol::Transport;
+
+/// DNS name a node's certificate must be issued for.
+pub fn node_name(node: u64) -> String {
+    format!("{node}.node.quarxnet")
+}
+
+fn tls_error<E: std::fmt::Display>(e: E) -> NetError {
+    NetError::Tls(e.to_string())
+}
+
+/// I/O errors raised by rustls carry the TLS failure; keep its text.
+fn map_io(e: io::Error) -> NetError {
+    match e.get_ref().and_then(|inner| inner.downcast_ref::<rustls::Error>()) {
+        Some(tls) => tls_error(tls),
+        None => e.into(),
+    }
+}
+
+/// Certificate chain and private key of this node.
+pub struct TlsIdentity {
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl TlsIdentity {
+    pub fn new(certs: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Self {
+        TlsIdentity { certs, key }
+    }
+
+    /// Parses a PEM certificate chain (leaf first) and a PEM private key.
+    pub fn from_pem(cert_pem: &[u8], key_pem: &[u8]) -> NetResult<Self> {
+        let certs = CertificateDer::pem_slice_iter(cert_pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(tls_error)?;
+        let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(tls_error)?;
+        Self::checked(certs, key)
+    }
+
+    pub fn from_pem_files<P: AsRef<Path>>(cert_path: P, key_path: P) -> NetResult<Self> {
+        let certs = CertificateDer::pem_file_iter(cert_path)
+            .map_err(tls_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(tls_error)?;
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(tls_error)?;
+        Self::checked(certs, key)
+    }
+
+    fn checked(certs: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> NetResult<Self> {
+        if certs.is_empty() {
+            return Err(NetError::Tls("no certificate in PEM input".into()));
+        }
+        Ok(TlsIdentity { certs, key })
+    }
+}
+
+impl Clone for TlsIdentity {
+    fn clone(&self) -> Self {
+        TlsIdentity { certs: self.certs.clone(), key: self.key.clone_key() }
+    }
+}
+
+/// Loads the CA certificates peers are verified against.
+pub fn load_roots_pem_file<P: AsRef<Path>>(path: P) -> NetResult<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(path).map_err(tls_error)? {
+        roots.add(cert.map_err(tls_error)?).map_err(tls_error)?;
+    }
+    if roots.is_empty() {
+        return Err(NetError::Tls("no CA certificate in PEM input".into()));
+    }
+    Ok(roots)
+}
+
+/// Client and server settings for one node; cheap to clone.
+#[derive(Clone)]
+pub struct TlsConfig {
+    identity: TlsIdentity,
+    roots: Arc<RootCertStore>,
+    client: Arc<ClientConfig>,
+    server: Arc<ServerConfig>,
+}
+
+impl TlsConfig {
+    /// Mutual TLS: we present `identity` on both sides of a connection and
+    /// require a certificate from the peer, verified against `roots`.
+    pub fn new(identity: TlsIdentity, roots: RootCertStore) -> NetResult<Self> {
+        let roots = Arc::new(roots);
+        let client = Self::client_config(&identity, &roots)?;
+        let server = Self::server_config(&identity, &roots, true)?;
+        Ok(TlsConfig { identity, roots, client, server })
+    }
+
+    /// Node certificate, key and CA bundle from PEM files.
+    pub fn from_pem_files<P: AsRef<Path>>(cert_path: P, key_path: P, ca_path: P) -> NetResult<Self> {
+        Self::new(TlsIdentity::from_pem_files(cert_path, key_path)?, load_roots_pem_file(ca_path)?)
+    }
+
+    /// Server side: accept clients without a certificate. Their
+    /// [`TlsTransport::peer_identity`] is then `None`.
+    pub fn with_optional_client_auth(mut self) -> NetResult<Self> {
+        self.server = Self::server_config(&self.identity, &self.roots, false)?;
+        Ok(self)
+    }
+
+    fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+        Arc::new(rustls::crypto::ring::default_provider())
+    }
+
+    fn client_config(id: &TlsIdentity, roots: &Arc<RootCertStore>) -> NetResult<Arc<ClientConfig>> {
+        let cfg = ClientConfig::builder_with_provider(Self::provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_root_certificates(roots.clone())
+            .with_client_auth_cert(id.certs.clone(), id.key.clone_key())
+            .map_err(tls_error)?;
+        Ok(Arc::new(cfg))
+    }
+
+    fn server_config(
+        id: &TlsIdentity,
+        roots: &Arc<RootCertStore>,
+        require_client: bool,
+    ) -> NetResult<Arc<ServerConfig>> {
+        let mut verifier = WebPkiClientVerifier::builder_with_provider(roots.clone(), Self::provider());
+        if !require_client {
+            verifier = verifier.allow_unauthenticated();
+        }
+        let cfg = ServerConfig::builder_with_provider(Self::provider())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .with_client_cert_verifier(verifier.build().map_err(tls_error)?)
+            .with_single_cert(id.certs.clone(), id.key.clone_key())
+            .map_err(tls_error)?;
+        Ok(Arc::new(cfg))
+    }
+}
+
+/// Authenticated certificate of the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    cert: CertificateDer<'static>,
+}
+
+impl PeerIdentity {
+    /// End-entity certificate, already verified against the CA.
+    pub fn certificate(&self) -> &CertificateDer<'static> {
+        &self.cert
+    }
+
+    /// True if the certificate was issued for [`node_name`]`(node)`.
+    pub fn is_node(&self, node: u64) -> bool {
+        let Ok(name) = ServerName::try_from(node_name(node)) else {
+            return false;
+        };
+        webpki::EndEntityCert::try_from(&self.cert)
+            .map(|c| c.verify_is_valid_for_subject_name(&name).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// Fails with [`NetError::PeerIdentityMismatch`] unless the peer may
+    /// act as `node`, e.g. the `node` from its Hello.
+    pub fn check_node(&self, node: u64) -> NetResult<()> {
+        if !self.is_node(node) {
+            return Err(NetError::PeerIdentityMismatch { node });
+        }
+        Ok(())
+    }
+}
+
+enum Stream {
+    Client(StreamOwned<ClientConnection, TcpStream>),
+    Server(StreamOwned<ServerConnection, TcpStream>),
+}
+
+/// Blocking [`Transport`] over TLS. The handshake completes inside
+/// `connect`/`accept`, so certificate errors surface there as
+/// [`NetError::Tls`].
+pub struct TlsTransport {
+    stream: Stream,
+}
+
+impl TlsTransport {
+    /// Connects and verifies that the server is `server_name`.
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        server_name: &str,
+        tcp: &TcpConfig,
+        tls: &TlsConfig,
+    ) -> NetResult<Self> {
+        Self::client(TcpTransport::connect(addr, tcp)?, server_name, tls)
+    }
+
+    /// Connects to the node `node`, i.e. requires its certificate to be
+    /// issued for [`node_name`]`(node)`.
+    pub fn connect_node<A: ToSocketAddrs>(
+        addr: A,
+        node: u64,
+        tcp: &TcpConfig,
+        tls: &TlsConfig,
+    ) -> NetResult<Self> {
+        Self::connect(addr, &node_name(node), tcp, tls)
+    }
+
+    /// Client side of TLS on an established TCP connection, before any
+    /// frame was exchanged on it.
+    pub fn client(t: TcpTransport, server_name: &str, tls: &TlsConfig) -> NetResult<Self> {
+        let name = ServerName::try_from(server_name.to_string()).map_err(tls_error)?;
+        let conn = ClientConnection::new(tls.client.clone(), name).map_err(tls_error)?;
+        let mut s = StreamOwned::new(conn, t.into_stream()?);
+        while s.conn.is_handshaking() {
+            s.conn.complete_io(&mut s.sock).map_err(map_io)?;
+        }
+        Ok(TlsTransport { stream: Stream::Client(s) })
+    }
+
+    /// Server side of TLS on an accepted TCP connection.
+    pub fn server(t: TcpTransport, tls: &TlsConfig) -> NetResult<Self> {
+        let conn = ServerConnection::new(tls.server.clone()).map_err(tls_error)?;
+        let mut s = StreamOwned::new(conn, t.into_stream()?);
+        while s.conn.is_handshaking() {
+            s.conn.complete_io(&mut s.sock).map_err(map_io)?;
+        }
+        Ok(TlsTransport { stream: Stream::Server(s) })
+    }
+
+    /// The peer's verified certificate; `None` only for a client accepted
+    /// under [`TlsConfig::with_optional_client_auth`].
+    pub fn peer_identity(&self) -> Option<PeerIdentity> {
+        let certs = match &self.stream {
+            Stream::Client(s) => s.conn.peer_certificates(),
+            Stream::Server(s) => s.conn.peer_certificates(),
+        };
+        certs
+            .and_then(|c| c.first())
+            .map(|cert| PeerIdentity { cert: cert.clone().into_owned() })
+    }
+
+    pub fn peer_addr(&self) -> NetResult<SocketAddr> {
+        Ok(self.sock().peer_addr()?)
+    }
+
+    pub fn local_addr(&self) -> NetResult<SocketAddr> {
+        Ok(self.sock().local_addr()?)
+    }
+
+    /// Sends close_notify and shuts down the socket.
+    pub fn shutdown(&mut self) -> NetResult<()> {
+        match &mut self.stream {
+            Stream::Client(s) => s.conn.send_close_notify(),
+            Stream::Server(s) => s.conn.send_close_notify(),
+        }
+        self.io().flush().map_err(map_io)?;
+        match self.sock().shutdown(std::net::Shutdown::Both) {
+            Err(e) if e.kind() == io::ErrorKind::NotConnected => Ok(()),
+            res => Ok(res?),
+        }
+    }
+
+    fn sock(&self) -> &TcpStream {
+        match &self.stream {
+            Stream::Client(s) => &s.sock,
+            Stream::Server(s) => &s.sock,
+        }
+    }
+
+    fn io(&mut self) -> &mut dyn ReadWrite {
+        match &mut self.stream {
+            Stream::Client(s) => s,
+            Stream::Server(s) => s,
+        }
+    }
+}
+
+trait ReadWrite: Read + Write {}
+impl<T: Read + Write> ReadWrite for T {}
+
+impl Transport for TlsTransport {
+    fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        self.send_vectored(&[data])
+    }
+
+    /// Parts are encrypted into TLS records as they come; the records go
+    /// out on the final flush.
+    fn send_vectored(&mut self, parts: &[&[u8]]) -> NetResult<()> {
+        let io = self.io();
+        for part in parts {
+            io.write_all(part).map_err(map_io)?;
+        }
+        io.flush().map_err(map_io)
+    }
+
+    fn recv_exact(&mut self, len: usize) -> NetResult<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        self.recv_exact_into(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn recv_exact_into(&mut self, buf: &mut [u8]) -> NetResult<()> {
+        self.io().read_exact(buf).map_err(map_io)
+    }
+}
+
+/// Accepting side of [`TlsTransport`].
+pub struct TlsTransportListener {
+    tcp: TcpTransportListener,
+    tls: TlsConfig,
+}
+
+impl TlsTransportListener {
+    pub fn bind<A: ToSocketAddrs>(addr: A, tcp: TcpConfig, tls: TlsConfig) -> NetResult<Self> {
+        Ok(TlsTransportListener { tcp: TcpTransportListener::bind(addr, tcp)?, tls })
+    }
+
+    /// Accepts a connection and runs the TLS handshake on it. A failed
+    /// handshake is returned as an error; the listener stays usable.
+    pub fn accept(&self) -> NetResult<(TlsTransport, SocketAddr)> {
+        let (t, peer) = self.tcp.accept()?;
+        Ok((TlsTransport::server(t, &self.tls)?, peer))
+    }
+
+    pub fn local_addr(&self) -> NetResult<SocketAddr> {
+        self.tcp.local_addr()
+    }
+}
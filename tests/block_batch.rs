@@ -0,0 +1,95 @@
+use quarxnet::error::NetError;
+use bytes::Bytes;
+use quarxnet::protocol::{
+    decode_block_batch, decode_block_batch_shared, encode_block_batch, BlockBatch, BlockEntry,
+    SharedBlockEntry,
+};
+use quarxtor_core::net_core::GetBlocksPayload;
+
+fn batch() -> BlockBatch {
+    BlockBatch {
+        entries: vec![
+            BlockEntry::Present { id: 10, data: b"ten".to_vec() },
+            BlockEntry::Missing { id: 11 },
+            BlockEntry::Present { id: 12, data: vec![] },
+            BlockEntry::Present { id: 99, data: vec![7; 1000] },
+        ],
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let bytes = encode_block_batch(&batch());
+    assert_eq!(&bytes[..4], &[0, 0, 0, 4]);
+    assert_eq!(decode_block_batch(&bytes).unwrap(), batch());
+
+    let empty = BlockBatch::default();
+    assert_eq!(decode_block_batch(&encode_block_batch(&empty)).unwrap(), empty);
+}
+
+#[test]
+fn shared_decode_slices_the_payload() {
+    let bytes = Bytes::from(encode_block_batch(&batch()));
+    let shared = decode_block_batch_shared(&bytes).unwrap();
+
+    let range = bytes.as_ptr_range();
+    for e in &shared.entries {
+        if let SharedBlockEntry::Present { data, .. } = e {
+            assert!(range.contains(&data.as_ptr()) || data.is_empty());
+        }
+    }
+    let req = GetBlocksPayload { ids: vec![10, 11, 13] };
+    assert_eq!(shared.coverage(&req), batch().coverage(&req));
+    assert_eq!(BlockBatch::from(shared), batch());
+
+    let mut corrupt = bytes.to_vec();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 1;
+    assert_eq!(
+        decode_block_batch_shared(&Bytes::from(corrupt)),
+        Err(NetError::BlockHashMismatch { id: 99 })
+    );
+}
+
+#[test]
+fn corrupted_block_is_reported_by_id() {
+    let mut bytes = encode_block_batch(&batch());
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    assert_eq!(decode_block_batch(&bytes), Err(NetError::BlockHashMismatch { id: 99 }));
+}
+
+#[test]
+fn length_and_framing_errors() {
+    let bytes = encode_block_batch(&batch());
+    // обрезанный буфер на любой позиции — ошибка декодирования, не паника
+    for cut in 0..bytes.len() {
+        assert!(decode_block_batch(&bytes[..cut]).is_err(), "cut at {cut}");
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(decode_block_batch(&trailing), Err(NetError::DecodeError));
+
+    let mut bad_tag = bytes;
+    bad_tag[4] = 7;
+    assert_eq!(decode_block_batch(&bad_tag), Err(NetError::DecodeError));
+
+    // огромный count без данных
+    assert_eq!(decode_block_batch(&[0xFF, 0xFF, 0xFF, 0xFF]), Err(NetError::DecodeError));
+}
+
+#[test]
+fn coverage_against_request() {
+    let req = GetBlocksPayload { ids: vec![10, 11, 12, 13] };
+    let cov = batch().coverage(&req);
+
+    assert_eq!(cov.satisfied, vec![10, 12]);
+    assert_eq!(cov.missing, vec![11]);
+    assert_eq!(cov.unanswered, vec![13]);
+    assert_eq!(cov.unexpected, vec![99]);
+    assert!(!cov.is_complete());
+
+    let exact = GetBlocksPayload { ids: vec![10, 11, 12, 99] };
+    assert!(batch().coverage(&exact).is_complete());
+}
@@ -0,0 +1,123 @@
+use quarxnet::capability::{negotiate, Capability, CapabilitySet};
+use quarxnet::error::NetError;
+use quarxnet::protocol::{
+    decode_caps, encode_caps, exchange_caps, make_frame, send_frame, FrameLimits,
+};
+use quarxnet::transport::memory::duplex;
+use quarxnet::frame::FrameKind;
+
+const A: Capability = Capability::from_bit(0);
+const B: Capability = Capability::from_bit(5);
+const C: Capability = Capability::from_bit(63);
+
+#[test]
+fn caps_roundtrip() {
+    let set = CapabilitySet::new()
+        .with(A)
+        .with(C)
+        .with_extension("x-zebra")
+        .unwrap()
+        .with_extension("x-alpha")
+        .unwrap();
+
+    let bytes = encode_caps(&set);
+    assert_eq!(&bytes[0..8], &(1u64 | 1 << 63).to_be_bytes());
+    // расширения пишутся в отсортированном порядке
+    assert_eq!(&bytes[8..10], &[0, 2]);
+    assert_eq!(&bytes[10..19], b"\x00\x07x-alpha");
+
+    assert_eq!(decode_caps(&bytes).unwrap(), set);
+    assert_eq!(decode_caps(&encode_caps(&CapabilitySet::new())).unwrap(), CapabilitySet::new());
+}
+
+#[test]
+fn caps_decode_rejects_malformed() {
+    let good = encode_caps(&CapabilitySet::new().with_extension("abc").unwrap());
+
+    assert_eq!(decode_caps(&good[..9]), Err(NetError::DecodeError));
+    assert_eq!(decode_caps(&good[..good.len() - 1]), Err(NetError::DecodeError));
+
+    let mut trailing = good.clone();
+    trailing.push(0);
+    assert_eq!(decode_caps(&trailing), Err(NetError::DecodeError));
+
+    let mut bad_utf8 = good;
+    let last = bad_utf8.len() - 1;
+    bad_utf8[last] = 0xFF;
+    assert_eq!(decode_caps(&bad_utf8), Err(NetError::DecodeError));
+}
+
+#[test]
+fn negotiate_intersects_bits_and_extensions() {
+    let local = CapabilitySet::new()
+        .with(A)
+        .with(B)
+        .with_extension("x-one")
+        .unwrap()
+        .with_extension("x-two")
+        .unwrap();
+    let remote = CapabilitySet::new().with(B).with(C).with_extension("x-two").unwrap();
+
+    let agreed = negotiate(&local, &remote);
+    assert!(!agreed.contains(A));
+    assert!(agreed.contains(B));
+    assert!(!agreed.contains(C));
+    assert!(agreed.has_extension("x-two"));
+    assert!(!agreed.has_extension("x-one"));
+    assert_eq!(agreed, negotiate(&remote, &local));
+}
+
+#[test]
+fn exchange_caps_over_transport() {
+    let (mut a, mut b) = duplex();
+    let ca = CapabilitySet::new().with(A).with(B);
+    let cb = CapabilitySet::new().with(B).with_extension("x-only-b").unwrap();
+
+    let t = std::thread::spawn(move || exchange_caps(&mut b, &cb).unwrap());
+    let agreed_a = exchange_caps(&mut a, &ca).unwrap();
+    let agreed_b = t.join().unwrap();
+
+    assert_eq!(agreed_a, CapabilitySet::new().with(B));
+    assert_eq!(agreed_a, agreed_b);
+}
+
+#[test]
+fn exchange_caps_rejects_other_frame_kinds() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut b, &make_frame(FrameKind::Ping, vec![])).unwrap();
+    assert_eq!(exchange_caps(&mut a, &CapabilitySet::new()), Err(NetError::InvalidFrame));
+}
+
+#[test]
+fn largest_set_fits_the_caps_frame() {
+    let name = "x".repeat(CapabilitySet::MAX_ENCODED_LEN - 12);
+    let set = CapabilitySet::new().with(A).with_extension(name).unwrap();
+    assert_eq!(encode_caps(&set).len(), set.encoded_len());
+    assert_eq!(set.encoded_len() as u32, FrameLimits::DEFAULT_CAPS_MAX);
+
+    let (mut a, mut b) = duplex();
+    let theirs = set.clone();
+    let t = std::thread::spawn(move || exchange_caps(&mut b, &theirs).unwrap());
+    assert_eq!(exchange_caps(&mut a, &set).unwrap(), set);
+    assert_eq!(t.join().unwrap(), set);
+}
+
+#[test]
+fn oversized_set_is_rejected() {
+    let too_large = NetError::FrameTooLarge {
+        kind: 2,
+        length: FrameLimits::DEFAULT_CAPS_MAX + 1,
+        max: FrameLimits::DEFAULT_CAPS_MAX,
+    };
+    let name = "x".repeat(CapabilitySet::MAX_ENCODED_LEN - 11);
+    assert_eq!(CapabilitySet::new().with_extension(name).err(), Some(too_large));
+
+    // много коротких имён упираются в тот же предел
+    let names = (0..20_000).map(|i| format!("x-{i}"));
+    assert!(matches!(CapabilitySet::from_parts(0, names), Err(NetError::FrameTooLarge { .. })));
+
+    let mut set = CapabilitySet::new();
+    set.insert_extension("x-one").unwrap();
+    set.insert_extension("x-one").unwrap();
+    assert_eq!(set.encoded_len(), 10 + 2 + 5);
+}
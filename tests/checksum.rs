@@ -0,0 +1,64 @@
+use quarxnet::capability::{negotiate, Capability, CapabilitySet};
+use quarxnet::error::NetError;
+use quarxnet::protocol::{
+    make_frame, recv_frame, send_frame_with, FrameOptions, Transport, FLAG_CHECKSUM,
+};
+use quarxnet::transport::memory::duplex;
+use quarxnet::frame::{Frame, FrameHeader, FrameKind};
+
+#[test]
+fn checksum_is_used_only_when_both_sides_advertise_it() {
+    let with = CapabilitySet::new().with(Capability::CRC32C);
+    let without = CapabilitySet::new();
+
+    assert!(FrameOptions::negotiated(&negotiate(&with, &with)).checksum);
+    assert!(!FrameOptions::negotiated(&negotiate(&with, &without)).checksum);
+    assert!(!FrameOptions::negotiated(&negotiate(&without, &with)).checksum);
+}
+
+#[test]
+fn trailer_layout_and_roundtrip() {
+    let (mut a, mut b) = duplex();
+    let opts = FrameOptions { checksum: true, ..FrameOptions::default() };
+    let frame = Frame {
+        header: FrameHeader { kind: FrameKind::PushBlocks, flags: 0x40, length: 3, request_id: None },
+        payload: vec![1, 2, 3].into(),
+    };
+
+    send_frame_with(&mut a, &frame, &opts).unwrap();
+    let wire = b.recv_exact(13).unwrap();
+    assert_eq!(&wire[..9], &[4, 0x40 | FLAG_CHECKSUM, 0, 0, 0, 3, 1, 2, 3]);
+    assert_eq!(&wire[9..], &crc32c::crc32c(&[1, 2, 3]).to_be_bytes());
+
+    send_frame_with(&mut a, &frame, &opts).unwrap();
+    let got = recv_frame(&mut b).unwrap();
+    assert_eq!(got.payload, vec![1, 2, 3]);
+    assert_eq!(got.header.length, 3);
+    // флаг контрольной суммы снимается при приёме, прикладные биты остаются
+    assert_eq!(got.header.flags, 0x40);
+}
+
+#[test]
+fn corrupted_payload_is_rejected() {
+    let (mut a, mut b) = duplex();
+    let (mut tap, mut out) = duplex();
+
+    let opts = FrameOptions { checksum: true, ..FrameOptions::default() };
+    send_frame_with(&mut tap, &make_frame(FrameKind::PushObject, vec![9; 32]), &opts).unwrap();
+    let mut wire = out.recv_exact(6 + 32 + 4).unwrap();
+    wire[20] ^= 0x10;
+
+    a.send(&wire).unwrap();
+    assert_eq!(recv_frame(&mut b).err(), Some(NetError::ChecksumMismatch { kind: 6 }));
+}
+
+#[test]
+fn plain_options_clear_a_stray_checksum_flag() {
+    let (mut a, mut b) = duplex();
+    let frame = Frame {
+        header: FrameHeader { kind: FrameKind::Ping, flags: FLAG_CHECKSUM, length: 1, request_id: None },
+        payload: vec![5].into(),
+    };
+    send_frame_with(&mut a, &frame, &FrameOptions::default()).unwrap();
+    assert_eq!(b.recv_exact(7).unwrap(), vec![7, 0, 0, 0, 0, 1, 5]);
+}
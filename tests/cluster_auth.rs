@@ -0,0 +1,104 @@
+#![cfg(feature = "cluster-auth")]
+
+use std::thread;
+
+use quarxnet::cluster_auth::{authenticate, cluster_handshake, ClusterSecret};
+use quarxnet::error::{ErrorCode, NetError, NetResult};
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    decode_auth, decode_error, encode_auth, make_frame, recv_frame, send_frame, AuthMethod,
+    AuthPayload, Session,
+};
+use quarxnet::transport::memory::{duplex, MemoryTransport};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+fn hello(node: u64) -> HelloPayload {
+    HelloPayload { node, version: ProtocolVersion { major: 1, minor: 0 } }
+}
+
+fn run_pair(
+    a: (u64, &str),
+    b: (u64, &str),
+) -> (NetResult<Session>, NetResult<Session>, MemoryTransport, MemoryTransport) {
+    let (mut ta, mut tb) = duplex();
+    let (b_node, b_secret) = (b.0, ClusterSecret::new(b.1).unwrap());
+    let peer = thread::spawn(move || {
+        let r = cluster_handshake(&mut tb, &hello(b_node), &b_secret, true);
+        (r, tb)
+    });
+    let ra = cluster_handshake(&mut ta, &hello(a.0), &ClusterSecret::new(a.1).unwrap(), true);
+    let (rb, tb) = peer.join().unwrap();
+    (ra, rb, ta, tb)
+}
+
+fn expect_unauthorized(t: &mut MemoryTransport) {
+    let f = recv_frame(t).unwrap();
+    assert_eq!(f.header.kind, FrameKind::Error);
+    assert_eq!(decode_error(&f.payload).unwrap().code, ErrorCode::Unauthorized);
+}
+
+fn auth_frame(method: AuthMethod, data: Vec<u8>) -> Frame {
+    make_frame(FrameKind::Auth, encode_auth(&AuthPayload { method, data }))
+}
+
+#[test]
+fn same_secret_authenticates_both_sides() {
+    let (ra, rb, _, _) = run_pair((1, "s3cret"), (2, "s3cret"));
+    assert_eq!(ra.unwrap().peer_node, 2);
+    assert_eq!(rb.unwrap().peer_node, 1);
+}
+
+#[test]
+fn different_secret_fails_with_error_frame() {
+    let (ra, rb, mut a, mut b) = run_pair((1, "s3cret"), (2, "other"));
+    assert_eq!(ra, Err(NetError::ClusterAuthFailed { node: 2 }));
+    assert_eq!(rb, Err(NetError::ClusterAuthFailed { node: 1 }));
+    expect_unauthorized(&mut a);
+    expect_unauthorized(&mut b);
+    assert_eq!(ErrorCode::from(&NetError::ClusterAuthFailed { node: 2 }), ErrorCode::Unauthorized);
+}
+
+#[test]
+fn peer_claiming_our_node_id_is_refused() {
+    let (ra, rb, _, _) = run_pair((1, "s3cret"), (1, "s3cret"));
+    assert_eq!(ra, Err(NetError::ClusterAuthFailed { node: 1 }));
+    assert_eq!(rb, Err(NetError::ClusterAuthFailed { node: 1 }));
+}
+
+#[test]
+fn recorded_response_cannot_be_replayed() {
+    let secret = ClusterSecret::new("s3cret").unwrap();
+
+    // атакующий записывает обмен узла 2 со своим вызовом
+    let (mut tap, mut b) = duplex();
+    let s = secret.clone();
+    let node2 = thread::spawn(move || authenticate(&mut b, &s, 2, 1, false));
+    send_frame(&mut tap, &auth_frame(AuthMethod::ClusterChallenge, vec![7; 32])).unwrap();
+    let recorded: Vec<_> = (0..2).map(|_| recv_frame(&mut tap).unwrap()).collect();
+    assert_eq!(decode_auth(&recorded[1].payload).unwrap().method, AuthMethod::ClusterResponse);
+    drop(tap);
+    assert!(node2.join().unwrap().is_err());
+
+    // и проигрывает его узлу 1 от имени узла 2
+    let (mut a, mut attacker) = duplex();
+    let node1 = thread::spawn(move || (authenticate(&mut a, &secret, 1, 2, true), a));
+    for f in &recorded {
+        send_frame(&mut attacker, f).unwrap();
+    }
+    let (r, _) = node1.join().unwrap();
+    assert_eq!(r, Err(NetError::ClusterAuthFailed { node: 2 }));
+
+    let _challenge = recv_frame(&mut attacker).unwrap();
+    let _response = recv_frame(&mut attacker).unwrap();
+    expect_unauthorized(&mut attacker);
+}
+
+#[test]
+fn secret_is_not_printed() {
+    assert!(!format!("{:?}", ClusterSecret::new("s3cret").unwrap()).contains("s3cret"));
+}
+
+#[test]
+fn empty_secret_is_a_config_error() {
+    assert!(matches!(ClusterSecret::new(""), Err(NetError::Config(_))));
+}
@@ -0,0 +1,121 @@
+#![cfg(all(feature = "lz4", feature = "zstd"))]
+
+use quarxnet::capability::{negotiate, Capability, CapabilitySet};
+use quarxnet::compress::Compression;
+use quarxnet::error::NetError;
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    encode_frame_into, recv_frame, recv_frame_with_limits, send_frame_with, FrameDecoder,
+    FrameLimits, FrameOptions, Transport, FLAG_CHECKSUM, FLAG_COMPRESSED, FLAG_REQUEST_ID,
+    FLAG_STREAM,
+};
+use quarxnet::transport::memory::duplex;
+
+const CODECS: [Compression; 2] = [Compression::Lz4, Compression::Zstd];
+
+fn opts(codec: Compression) -> FrameOptions {
+    FrameOptions { compression: Some(codec), ..FrameOptions::default() }
+}
+
+/// Highly compressible payload of `len` bytes.
+fn blocks(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i / 64) as u8).collect()
+}
+
+fn wire(frame: &Frame, opts: &FrameOptions) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_frame_into(frame, opts, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn negotiation_prefers_zstd() {
+    let both = Compression::advertise(CapabilitySet::new());
+    assert!(both.contains(Capability::LZ4) && both.contains(Capability::ZSTD));
+    let lz4_only = CapabilitySet::new().with(Capability::LZ4);
+
+    assert_eq!(FrameOptions::negotiated(&negotiate(&both, &both)).compression, Some(Compression::Zstd));
+    assert_eq!(FrameOptions::negotiated(&negotiate(&both, &lz4_only)).compression, Some(Compression::Lz4));
+    assert_eq!(FrameOptions::negotiated(&negotiate(&both, &CapabilitySet::new())).compression, None);
+}
+
+#[test]
+fn compressed_frames_arrive_intact() {
+    let payload = blocks(64 * 1024);
+    for codec in CODECS {
+        let mut frame = Frame::new(FrameKind::PushObject, payload.clone()).with_request_id(9);
+        frame.header.flags = FLAG_STREAM;
+        let opts = FrameOptions { checksum: true, ..opts(codec) };
+
+        let w = wire(&frame, &opts);
+        assert_eq!(w[1], FLAG_STREAM | FLAG_CHECKSUM | FLAG_COMPRESSED | FLAG_REQUEST_ID);
+        assert!(w.len() < payload.len() / 3, "{codec:?}: {} bytes", w.len());
+
+        let (mut a, mut b) = duplex();
+        send_frame_with(&mut a, &frame, &opts).unwrap();
+        assert_eq!(recv_frame(&mut b), Ok(frame.clone()));
+
+        let mut d = FrameDecoder::new();
+        assert_eq!(d.decode(&w), Ok(vec![frame]));
+    }
+}
+
+#[test]
+fn small_and_incompressible_payloads_are_sent_plain() {
+    let small = Frame::new(FrameKind::PushBlocks, blocks(FrameOptions::DEFAULT_COMPRESS_THRESHOLD - 1));
+    let mut x = 0x9E37_79B9_7F4A_7C15u64;
+    let noise: Vec<u8> = (0..4096)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect();
+    let noisy = Frame::new(FrameKind::PushBlocks, noise);
+
+    for codec in CODECS {
+        for f in [&small, &noisy] {
+            let w = wire(f, &opts(codec));
+            assert_eq!(w[1] & FLAG_COMPRESSED, 0);
+            assert_eq!(w.len(), 6 + f.payload.len());
+        }
+    }
+}
+
+#[test]
+fn sender_flag_is_not_trusted() {
+    let mut f = Frame::new(FrameKind::PushBlocks, vec![1, 2, 3]);
+    f.header.flags = FLAG_COMPRESSED;
+    let (mut a, mut b) = duplex();
+    send_frame_with(&mut a, &f, &FrameOptions::default()).unwrap();
+    assert_eq!(recv_frame(&mut b).unwrap().header.flags, 0);
+}
+
+#[test]
+fn decompressed_size_is_bounded_by_frame_limit() {
+    let limits = FrameLimits::default().with_kind_max(FrameKind::PushBlocks, 4096);
+    let bomb = Frame::new(FrameKind::PushBlocks, vec![0; 1 << 20]);
+    let w = wire(&bomb, &opts(Compression::Zstd));
+    assert!(w.len() < 4096);
+
+    let (mut a, mut b) = duplex();
+    a.send(&w).unwrap();
+    assert_eq!(
+        recv_frame_with_limits(&mut b, &limits),
+        Err(NetError::FrameTooLarge { kind: 4, length: 1 << 20, max: 4096 })
+    );
+}
+
+#[test]
+fn understated_length_is_rejected() {
+    for codec in CODECS {
+        let mut w = wire(&Frame::new(FrameKind::PushBlocks, blocks(16 * 1024)), &opts(codec));
+        // байты 7..11 — исходная длина после байта кодека
+        w[7..11].copy_from_slice(&100u32.to_be_bytes());
+
+        let (mut a, mut b) = duplex();
+        a.send(&w).unwrap();
+        assert_eq!(recv_frame(&mut b), Err(NetError::DecodeError), "{codec:?}");
+    }
+}
@@ -0,0 +1,98 @@
+use std::thread;
+
+use quarxnet::capability::CapabilitySet;
+use quarxnet::error::{ErrorCode, NetError};
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    decode_error, encode_error, exchange_caps, make_error_response, make_frame, make_response,
+    recv_frame, send_frame, ErrorPayload, RpcClient,
+};
+use quarxnet::transport::memory::duplex;
+
+#[test]
+fn error_payload_roundtrip() {
+    let with_ref = ErrorPayload { code: ErrorCode::NotFound, request: Some(17), message: "no object 5".into() };
+    let bytes = encode_error(&with_ref);
+    assert_eq!(&bytes[..7], &[0, 1, 1, 0, 0, 0, 17]);
+    assert_eq!(decode_error(&bytes).unwrap(), with_ref);
+
+    let bare = ErrorPayload { code: ErrorCode::Other(900), request: None, message: String::new() };
+    assert_eq!(encode_error(&bare), vec![0x03, 0x84, 0, 0, 0]);
+    assert_eq!(decode_error(&encode_error(&bare)).unwrap(), bare);
+}
+
+#[test]
+fn error_payload_rejects_malformed() {
+    assert_eq!(decode_error(&[0, 1]), Err(NetError::DecodeError));
+    assert_eq!(decode_error(&[0, 1, 2, 0, 0]), Err(NetError::DecodeError));
+    assert_eq!(decode_error(&[0, 1, 1, 0, 0]), Err(NetError::DecodeError));
+    assert_eq!(decode_error(&[0, 1, 0, 0, 2, b'x']), Err(NetError::DecodeError));
+    assert_eq!(decode_error(&[0, 1, 0, 0, 1, 0xFF]), Err(NetError::DecodeError));
+}
+
+#[test]
+fn overlong_message_is_truncated_on_a_char_boundary() {
+    let p = ErrorPayload { code: ErrorCode::Internal, request: None, message: "я".repeat(40_000) };
+    let back = decode_error(&encode_error(&p)).unwrap();
+    assert_eq!(back.message.len(), 65_534);
+}
+
+#[test]
+fn codes_map_to_and_from_net_error() {
+    for c in 0..=20u16 {
+        assert_eq!(ErrorCode::from_u16(c).to_u16(), c);
+    }
+    assert_eq!(
+        ErrorCode::from(&NetError::FrameTooLarge { kind: 4, length: 10, max: 1 }),
+        ErrorCode::TooLarge
+    );
+    assert_eq!(ErrorCode::from(&NetError::DecodeError), ErrorCode::Malformed);
+
+    let remote: NetError = ErrorPayload { code: ErrorCode::Busy, request: None, message: "later".into() }.into();
+    assert_eq!(remote, NetError::Remote { code: ErrorCode::Busy, message: "later".into() });
+    assert_eq!(ErrorCode::from(&remote), ErrorCode::Busy);
+}
+
+#[test]
+fn rpc_client_surfaces_remote_errors() {
+    let (a, mut b) = duplex();
+    let server = thread::spawn(move || {
+        let ok = recv_frame(&mut b).unwrap();
+        let missing = recv_frame(&mut b).unwrap();
+        send_frame(&mut b, &make_error_response(&missing, ErrorCode::NotFound, "object 2 not found")).unwrap();
+        send_frame(&mut b, &make_response(&ok, FrameKind::PushObject, b"one".to_vec())).unwrap();
+    });
+
+    let mut client = RpcClient::new(a);
+    assert_eq!(
+        client.get_objects(&[1, 2]).err(),
+        Some(NetError::Remote { code: ErrorCode::NotFound, message: "object 2 not found".into() })
+    );
+
+    server.join().unwrap();
+}
+
+#[test]
+fn error_response_references_the_request() {
+    let req = Frame::new(FrameKind::GetObject, vec![0; 8]).with_request_id(33);
+    let resp = make_error_response(&req, ErrorCode::NotFound, "gone");
+
+    assert_eq!(resp.header.kind, FrameKind::Error);
+    assert_eq!(resp.header.request_id, Some(33));
+    assert_eq!(
+        decode_error(&resp.payload).unwrap(),
+        ErrorPayload { code: ErrorCode::NotFound, request: Some(33), message: "gone".into() }
+    );
+}
+
+#[test]
+fn caps_exchange_reports_peer_error() {
+    let (mut a, mut b) = duplex();
+    let caps = make_frame(FrameKind::Caps, vec![]);
+    send_frame(&mut b, &make_error_response(&caps, ErrorCode::UnsupportedVersion, "v9 only")).unwrap();
+
+    assert_eq!(
+        exchange_caps(&mut a, &CapabilitySet::new()),
+        Err(NetError::Remote { code: ErrorCode::UnsupportedVersion, message: "v9 only".into() })
+    );
+}
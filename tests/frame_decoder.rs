@@ -0,0 +1,140 @@
+use proptest::prelude::*;
+
+use quarxnet::error::NetError;
+use quarxnet::protocol::{FrameDecoder, FLAG_CHECKSUM, FLAG_COMPRESSED, FLAG_REQUEST_ID};
+use quarxnet::frame::{Frame, FrameKind};
+
+/// (kind byte, flags, request id, payload)
+type RawFrame = (u8, u8, Option<u32>, Vec<u8>);
+
+/// Wire encoding; the request id is written only if its flag is set.
+fn encode(frames: &[RawFrame]) -> Vec<u8> {
+    let mut v = Vec::new();
+    for (kind, flags, id, payload) in frames {
+        v.push(*kind);
+        v.push(*flags);
+        v.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        let body_at = v.len();
+        if flags & FLAG_REQUEST_ID != 0 {
+            v.extend_from_slice(&id.unwrap_or(0).to_be_bytes());
+        }
+        v.extend_from_slice(payload);
+        if flags & FLAG_CHECKSUM != 0 {
+            let crc = crc32c::crc32c(&v[body_at..]);
+            v.extend_from_slice(&crc.to_be_bytes());
+        }
+    }
+    v
+}
+
+/// What the decoder hands back: protocol flags are consumed and the
+/// request id surfaces in the header.
+fn expected(frames: &[RawFrame]) -> Vec<RawFrame> {
+    frames
+        .iter()
+        .map(|(k, f, id, p)| {
+            let id = if f & FLAG_REQUEST_ID != 0 { Some(id.unwrap_or(0)) } else { None };
+            (*k, f & !(FLAG_CHECKSUM | FLAG_REQUEST_ID), id, p.clone())
+        })
+        .collect()
+}
+
+fn raw(f: &Frame) -> RawFrame {
+    let kind = match f.header.kind {
+        FrameKind::Hello      => 1,
+        FrameKind::Caps       => 2,
+        FrameKind::GetBlocks  => 3,
+        FrameKind::PushBlocks => 4,
+        FrameKind::GetObject  => 5,
+        FrameKind::PushObject => 6,
+        FrameKind::Ping       => 7,
+        FrameKind::Pong       => 8,
+        FrameKind::Error      => 9,
+        FrameKind::Goodbye    => 10,
+        FrameKind::Auth       => 11,
+        FrameKind::Extension(b) => b,
+    };
+    assert_eq!(f.header.length as usize, f.payload.len());
+    (kind, f.header.flags, f.header.request_id, f.payload.to_vec())
+}
+
+fn decode_chunks<'a, I: IntoIterator<Item = &'a [u8]>>(chunks: I) -> (Vec<RawFrame>, FrameDecoder) {
+    let mut d = FrameDecoder::new();
+    let mut out = Vec::new();
+    for c in chunks {
+        out.extend(d.decode(c).unwrap().iter().map(raw));
+    }
+    (out, d)
+}
+
+fn frames_strategy() -> impl Strategy<Value = Vec<RawFrame>> {
+    prop::collection::vec(
+        (
+            1u8..=11,
+            // сжатые кадры проверяются в tests/compression.rs
+            any::<u8>().prop_map(|f| f & !FLAG_COMPRESSED),
+            any::<Option<u32>>(),
+            prop::collection::vec(any::<u8>(), 0..40),
+        ),
+        0..6,
+    )
+}
+
+proptest! {
+    #[test]
+    fn every_two_way_split_yields_same_frames(frames in frames_strategy()) {
+        let stream = encode(&frames);
+        for i in 0..=stream.len() {
+            let (got, d) = decode_chunks([&stream[..i], &stream[i..]]);
+            prop_assert_eq!(&got, &expected(&frames), "split at {}", i);
+            prop_assert!(d.is_idle());
+        }
+    }
+
+    #[test]
+    fn random_chunking_yields_same_frames(
+        frames in frames_strategy(),
+        cuts in prop::collection::vec(any::<prop::sample::Index>(), 0..12),
+    ) {
+        let stream = encode(&frames);
+        let mut points: Vec<usize> = cuts.iter().map(|ix| ix.index(stream.len() + 1)).collect();
+        points.push(0);
+        points.push(stream.len());
+        points.sort_unstable();
+
+        let chunks = points.windows(2).map(|w| &stream[w[0]..w[1]]);
+        let (got, d) = decode_chunks(chunks);
+        prop_assert_eq!(got, expected(&frames));
+        prop_assert!(d.is_idle());
+    }
+
+    #[test]
+    fn truncated_stream_leaves_partial_state(frames in frames_strategy(), cut in 1usize..6) {
+        let stream = encode(&frames);
+        prop_assume!(stream.len() >= cut);
+        let (got, d) = decode_chunks([&stream[..stream.len() - cut]]);
+        prop_assert!(got.len() < frames.len());
+        prop_assert!(!d.is_idle());
+    }
+}
+
+#[test]
+fn byte_at_a_time() {
+    let frames = vec![
+        (1, 0, None, vec![0; 12]),
+        (8, 7, Some(42), vec![]),
+        (6, 0, None, (0..=255).collect()),
+    ];
+    let stream = encode(&frames);
+    let (got, d) = decode_chunks(stream.chunks(1));
+    assert_eq!(got, expected(&frames));
+    assert!(d.is_idle());
+    assert_eq!(d.buffered(), 0);
+}
+
+#[test]
+fn invalid_kind_is_reported_once_header_is_complete() {
+    let mut d = FrameDecoder::new();
+    assert_eq!(d.decode(&[0x2A, 0, 0, 0, 0]).unwrap().len(), 0);
+    assert_eq!(d.decode(&[0]).err(), Some(NetError::InvalidFrame));
+}
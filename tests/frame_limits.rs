@@ -0,0 +1,54 @@
+use quarxnet::error::NetError;
+use quarxnet::protocol::{
+    make_frame, recv_frame, recv_frame_with_limits, send_frame, FrameDecoder, FrameLimits, Transport,
+};
+use quarxnet::transport::memory::duplex;
+use quarxnet::frame::FrameKind;
+
+#[test]
+fn defaults_keep_control_frames_tiny() {
+    let l = FrameLimits::default();
+    assert_eq!(l.max_for(&FrameKind::Hello), 256);
+    assert_eq!(l.max_for(&FrameKind::Ping), 256);
+    assert!(l.max_for(&FrameKind::PushBlocks) >= 16 * 1024 * 1024);
+}
+
+#[test]
+fn oversized_header_is_rejected_before_payload_is_read() {
+    let (mut a, mut b) = duplex();
+    // 4 GiB - 1 PushObject, но payload не отправляем вовсе
+    a.send(&[6, 0, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap();
+    a.send(&[1, 2, 3]).unwrap();
+
+    assert_eq!(
+        recv_frame(&mut b).err(),
+        Some(NetError::FrameTooLarge { kind: 6, length: u32::MAX, max: 64 * 1024 * 1024 })
+    );
+    // следующие байты не тронуты — payload не читался
+    assert_eq!(b.recv_exact(3).unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn per_connection_and_per_kind_overrides() {
+    let limits = FrameLimits::uniform(10).with_kind_max(FrameKind::PushBlocks, 100);
+    let (mut a, mut b) = duplex();
+
+    send_frame(&mut a, &make_frame(FrameKind::PushBlocks, vec![0; 100])).unwrap();
+    assert_eq!(recv_frame_with_limits(&mut b, &limits).unwrap().payload.len(), 100);
+
+    send_frame(&mut a, &make_frame(FrameKind::Ping, vec![0; 11])).unwrap();
+    assert_eq!(
+        recv_frame_with_limits(&mut b, &limits).err(),
+        Some(NetError::FrameTooLarge { kind: 7, length: 11, max: 10 })
+    );
+}
+
+#[test]
+fn decoder_enforces_limits() {
+    let mut d = FrameDecoder::with_limits(FrameLimits::uniform(4));
+    assert_eq!(d.decode(&[5, 0, 0, 0, 0, 4, 1, 2, 3, 4]).unwrap().len(), 1);
+    assert_eq!(
+        d.decode(&[5, 0, 0, 0, 0, 5]).err(),
+        Some(NetError::FrameTooLarge { kind: 5, length: 5, max: 4 })
+    );
+}
@@ -0,0 +1,105 @@
+use quarxnet::error::{NetError, NetResult};
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    recv_frame, recv_frame_with_limits, send_frame, FrameDecoder, FrameLimits, Transport,
+    FLAG_IGNORABLE,
+};
+use quarxnet::registry::{ExtensionFrame, ExtensionKind, FrameRegistry};
+use quarxnet::transport::memory::duplex;
+
+#[derive(Debug, PartialEq)]
+struct Gossip {
+    epoch: u32,
+}
+
+impl ExtensionFrame for Gossip {
+    const KIND: u8 = 0x50;
+    const NAME: &'static str = "gossip";
+    const MAX_LEN: u32 = 4;
+
+    fn encode_payload(&self) -> Vec<u8> {
+        self.epoch.to_be_bytes().to_vec()
+    }
+
+    fn decode_payload(b: &[u8]) -> NetResult<Self> {
+        let b: [u8; 4] = b.try_into().map_err(|_| NetError::DecodeError)?;
+        Ok(Gossip { epoch: u32::from_be_bytes(b) })
+    }
+}
+
+fn limits() -> FrameLimits {
+    FrameLimits::default().with_registry(FrameRegistry::new().with::<Gossip>())
+}
+
+fn ping() -> Frame {
+    Frame::new(FrameKind::Ping, vec![7])
+}
+
+#[test]
+fn registered_kind_roundtrips() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Gossip { epoch: 12 }.to_frame()).unwrap();
+
+    let f = recv_frame_with_limits(&mut b, &limits()).unwrap();
+    assert_eq!(f.header.kind, FrameKind::Extension(0x50));
+    assert_eq!(f.header.flags, FLAG_IGNORABLE);
+    assert_eq!(Gossip::from_frame(&f), Ok(Gossip { epoch: 12 }));
+    assert_eq!(Gossip::from_frame(&ping()), Err(NetError::InvalidFrame));
+}
+
+#[test]
+fn unknown_ignorable_kinds_are_skipped() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Gossip { epoch: 1 }.to_frame()).unwrap();
+    // вид из более новой версии протокола
+    let mut newer = Frame::new(FrameKind::Extension(0x3f), vec![0; 100]);
+    newer.header.flags = FLAG_IGNORABLE;
+    send_frame(&mut a, &newer).unwrap();
+    send_frame(&mut a, &ping()).unwrap();
+
+    assert_eq!(recv_frame(&mut b), Ok(ping()));
+}
+
+#[test]
+fn unknown_kinds_without_flag_are_fatal() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Frame::new(FrameKind::Extension(0x60), vec![1])).unwrap();
+    assert_eq!(recv_frame_with_limits(&mut b, &limits()), Err(NetError::InvalidFrame));
+}
+
+#[test]
+fn registered_max_len_is_enforced() {
+    let (mut a, mut b) = duplex();
+    let mut big = Frame::new(FrameKind::Extension(Gossip::KIND), vec![0; 5]);
+    big.header.flags = FLAG_IGNORABLE;
+    send_frame(&mut a, &big).unwrap();
+
+    assert_eq!(
+        recv_frame_with_limits(&mut b, &limits()),
+        Err(NetError::FrameTooLarge { kind: 0x50, length: 5, max: 4 })
+    );
+    assert_eq!(limits().with_kind_max(FrameKind::Extension(0x50), 8).max_for(&big.header.kind), 8);
+}
+
+#[test]
+fn decoder_skips_unknown_kinds_across_splits() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Gossip { epoch: 3 }.to_frame()).unwrap();
+    send_frame(&mut a, &ping()).unwrap();
+    let n = 2 * 6 + 4 + 1;
+    let bytes = b.recv_exact(n).unwrap();
+
+    for i in 0..=bytes.len() {
+        let mut d = FrameDecoder::new();
+        let mut got = d.decode(&bytes[..i]).unwrap();
+        got.extend(d.decode(&bytes[i..]).unwrap());
+        assert_eq!(got, vec![ping()], "split at {i}");
+        assert!(d.is_idle());
+    }
+}
+
+#[test]
+#[should_panic(expected = "reserved")]
+fn protocol_kinds_cannot_be_registered() {
+    FrameRegistry::new().register_kind(ExtensionKind { kind: 9, name: "error", max_len: 1 });
+}
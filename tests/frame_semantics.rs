@@ -0,0 +1,199 @@
+//! Wire-level cases shared by the blocking and the async frame paths.
+//! Every case is fed through `recv_frame` and, with `--features async`,
+//! through `recv_frame_async`; both must produce identical results.
+
+use quarxnet::error::{NetError, NetResult};
+use quarxnet::protocol::{recv_frame, send_frame, Transport};
+use quarxnet::transport::memory::duplex;
+use quarxnet::frame::{Frame, FrameHeader, FrameKind};
+
+/// (kind byte, flags, payload) — comparable view of a `Frame`.
+type Seen = NetResult<(u8, u8, Vec<u8>)>;
+
+fn kind_byte(k: &FrameKind) -> u8 {
+    match k {
+        FrameKind::Hello      => 1,
+        FrameKind::Caps       => 2,
+        FrameKind::GetBlocks  => 3,
+        FrameKind::PushBlocks => 4,
+        FrameKind::GetObject  => 5,
+        FrameKind::PushObject => 6,
+        FrameKind::Ping       => 7,
+        FrameKind::Pong       => 8,
+        FrameKind::Error      => 9,
+        FrameKind::Goodbye    => 10,
+        FrameKind::Auth       => 11,
+        FrameKind::Extension(b) => *b,
+    }
+}
+
+fn seen(r: NetResult<Frame>) -> Seen {
+    r.map(|f| (kind_byte(&f.header.kind), f.header.flags, f.payload.to_vec()))
+}
+
+fn frame(kind: FrameKind, flags: u8, payload: Vec<u8>) -> Frame {
+    Frame {
+        header: FrameHeader { kind, flags, length: payload.len() as u32, request_id: None },
+        payload: payload.into(),
+    }
+}
+
+/// Appends the CRC32C of `frame[payload_at..]`, optionally flipping a
+/// payload bit afterwards.
+fn with_crc(mut frame: Vec<u8>, payload_at: usize, corrupt: bool) -> Vec<u8> {
+    let crc = crc32c::crc32c(&frame[payload_at..]);
+    if corrupt {
+        frame[payload_at] ^= 1;
+    }
+    frame.extend_from_slice(&crc.to_be_bytes());
+    frame
+}
+
+/// Input stream and the frames/error a receiver must observe, in order.
+/// The receiver stops after the first error.
+fn cases() -> Vec<(&'static str, Vec<u8>, Vec<Seen>)> {
+    vec![
+        (
+            "two frames then eof",
+            vec![7, 0, 0, 0, 0, 2, 0xAA, 0xBB, 8, 0x40, 0, 0, 0, 0],
+            vec![
+                Ok((7, 0, vec![0xAA, 0xBB])),
+                Ok((8, 0x40, vec![])),
+                Err(NetError::ConnectionClosed),
+            ],
+        ),
+        ("empty stream", vec![], vec![Err(NetError::ConnectionClosed)]),
+        ("truncated header", vec![1, 0, 0], vec![Err(NetError::ConnectionClosed)]),
+        (
+            "truncated payload",
+            vec![4, 0, 0, 0, 0, 5, 1, 2],
+            vec![Err(NetError::ConnectionClosed)],
+        ),
+        ("unknown kind 0", vec![0, 0, 0, 0, 0, 0], vec![Err(NetError::InvalidFrame)]),
+        ("unknown kind 0x7F", vec![0x7F, 0, 0, 0, 0, 0], vec![Err(NetError::InvalidFrame)]),
+        (
+            "checksummed frame",
+            with_crc(vec![4, 0x41, 0, 0, 0, 3, 1, 2, 3], 6, false),
+            vec![Ok((4, 0x40, vec![1, 2, 3])), Err(NetError::ConnectionClosed)],
+        ),
+        (
+            "corrupted checksummed frame",
+            with_crc(vec![4, 0x01, 0, 0, 0, 3, 1, 2, 3], 6, true),
+            vec![Err(NetError::ChecksumMismatch { kind: 4 })],
+        ),
+        (
+            "missing checksum trailer",
+            vec![4, 0x01, 0, 0, 0, 3, 1, 2, 3, 0xEE],
+            vec![Err(NetError::ConnectionClosed)],
+        ),
+        (
+            "oversized ping",
+            vec![7, 0, 0x40, 0, 0, 0],
+            vec![Err(NetError::FrameTooLarge { kind: 7, length: 0x4000_0000, max: 256 })],
+        ),
+    ]
+}
+
+fn sample_frames() -> Vec<Frame> {
+    vec![
+        frame(FrameKind::Hello, 0, vec![0; 12]),
+        frame(FrameKind::PushObject, 0x80, (0..=255).collect()),
+        frame(FrameKind::Pong, 0, vec![]),
+    ]
+}
+
+fn recv_until_error_sync(input: &[u8]) -> Vec<Seen> {
+    let (mut a, mut b) = duplex();
+    a.send(input).unwrap();
+    a.close();
+
+    let mut out = Vec::new();
+    loop {
+        let r = seen(recv_frame(&mut b));
+        let stop = r.is_err();
+        out.push(r);
+        if stop {
+            return out;
+        }
+    }
+}
+
+fn encode_sync(frames: &[Frame]) -> Vec<u8> {
+    let (mut a, mut b) = duplex();
+    for f in frames {
+        send_frame(&mut a, f).unwrap();
+    }
+    a.close();
+
+    let mut bytes = Vec::new();
+    while let Ok(chunk) = b.recv_exact(1) {
+        bytes.extend(chunk);
+    }
+    bytes
+}
+
+#[test]
+fn sync_cases() {
+    for (name, input, expected) in cases() {
+        assert_eq!(recv_until_error_sync(&input), expected, "case: {name}");
+    }
+}
+
+#[test]
+fn sync_send_recv_roundtrip() {
+    let bytes = encode_sync(&sample_frames());
+    let got = recv_until_error_sync(&bytes);
+    let mut want: Vec<Seen> = sample_frames().into_iter().map(|f| seen(Ok(f))).collect();
+    want.push(Err(NetError::ConnectionClosed));
+    assert_eq!(got, want);
+}
+
+#[cfg(feature = "async")]
+mod async_path {
+    use super::*;
+    use quarxnet::protocol::{recv_frame_async, send_frame_async};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn recv_until_error_async(input: &[u8]) -> Vec<Seen> {
+        let (mut a, mut b) = tokio::io::duplex(64);
+        let input = input.to_vec();
+        let writer = async move {
+            a.write_all(&input).await.unwrap();
+            drop(a);
+        };
+        let reader = async move {
+            let mut out = Vec::new();
+            loop {
+                let r = seen(recv_frame_async(&mut b).await);
+                let stop = r.is_err();
+                out.push(r);
+                if stop {
+                    return out;
+                }
+            }
+        };
+        tokio::join!(writer, reader).1
+    }
+
+    #[tokio::test]
+    async fn async_cases_match_sync() {
+        for (name, input, expected) in cases() {
+            let got = recv_until_error_async(&input).await;
+            assert_eq!(got, expected, "case: {name}");
+            assert_eq!(got, recv_until_error_sync(&input), "case: {name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn async_send_matches_sync_bytes() {
+        let (mut a, mut b) = tokio::io::duplex(1 << 16);
+        for f in &sample_frames() {
+            send_frame_async(&mut a, f).await.unwrap();
+        }
+        drop(a);
+
+        let mut bytes = Vec::new();
+        b.read_to_end(&mut bytes).await.unwrap();
+        assert_eq!(bytes, encode_sync(&sample_frames()));
+    }
+}
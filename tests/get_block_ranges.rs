@@ -0,0 +1,113 @@
+use quarxnet::capability::{negotiate, Capability, CapabilitySet};
+use quarxnet::error::NetError;
+use quarxnet::protocol::{
+    decode_get_block_ranges, decode_get_blocks, decode_get_blocks_for, encode_get_block_ranges,
+    encode_get_blocks_for, BlockSelector, FrameLimits, GetBlockRanges,
+};
+
+fn sample() -> GetBlockRanges {
+    GetBlockRanges {
+        selectors: vec![
+            BlockSelector::Id(7),
+            BlockSelector::Range { start: 100, end: 104 },
+            BlockSelector::Id(3),
+        ],
+        max_bytes: Some(1 << 20),
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let bytes = encode_get_block_ranges(&sample());
+    assert_eq!(decode_get_block_ranges(&bytes).unwrap(), sample());
+
+    let unbudgeted = GetBlockRanges { max_bytes: None, ..sample() };
+    assert_eq!(decode_get_block_ranges(&encode_get_block_ranges(&unbudgeted)).unwrap(), unbudgeted);
+}
+
+#[test]
+fn a_long_run_costs_one_selector() {
+    let ids: Vec<u64> = (5_000..105_000).collect();
+    let req = GetBlockRanges::from_ids(&ids);
+    assert_eq!(req.selectors, vec![BlockSelector::Range { start: 5_000, end: 105_000 }]);
+    assert_eq!(req.id_count(), 100_000);
+    assert!(encode_get_block_ranges(&req).len() < 32);
+    assert!(req.ids().unwrap().eq(ids.iter().copied()));
+}
+
+#[test]
+fn from_ids_keeps_order_and_singletons() {
+    let req = GetBlockRanges::from_ids(&[9, 1, 2, 3, 5, u64::MAX]);
+    assert_eq!(
+        req.selectors,
+        vec![
+            BlockSelector::Id(9),
+            BlockSelector::Range { start: 1, end: 4 },
+            BlockSelector::Id(5),
+            BlockSelector::Id(u64::MAX),
+        ]
+    );
+    assert!(req.ids().unwrap().eq([9, 1, 2, 3, 5, u64::MAX]));
+}
+
+#[test]
+fn rejects_malformed() {
+    let bytes = encode_get_block_ranges(&sample());
+    for cut in 0..bytes.len() {
+        assert!(decode_get_block_ranges(&bytes[..cut]).is_err(), "cut at {cut}");
+    }
+
+    let empty_range = GetBlockRanges {
+        selectors: vec![BlockSelector::Range { start: 4, end: 4 }],
+        max_bytes: None,
+    };
+    assert_eq!(
+        decode_get_block_ranges(&encode_get_block_ranges(&empty_range)),
+        Err(NetError::DecodeError)
+    );
+    assert_eq!(decode_get_block_ranges(&[2, 0, 0, 0, 0]), Err(NetError::DecodeError));
+}
+
+#[test]
+fn old_peers_get_the_flat_list() {
+    let ranged = CapabilitySet::new().with(Capability::BLOCK_RANGES);
+    let old = CapabilitySet::new();
+
+    let caps = negotiate(&ranged, &old);
+    let bytes = encode_get_blocks_for(&caps, &sample()).unwrap();
+    assert_eq!(decode_get_blocks(&bytes).unwrap().ids, vec![7, 100, 101, 102, 103, 3]);
+
+    let back = decode_get_blocks_for(&caps, &bytes).unwrap();
+    assert_eq!(back.max_bytes, None);
+    assert!(back.ids().unwrap().eq(sample().ids().unwrap()));
+
+    let caps = negotiate(&ranged, &ranged);
+    let bytes = encode_get_blocks_for(&caps, &sample()).unwrap();
+    assert_eq!(decode_get_blocks_for(&caps, &bytes).unwrap(), sample());
+}
+
+#[test]
+fn huge_range_is_not_expanded() {
+    let huge = GetBlockRanges {
+        selectors: vec![BlockSelector::Range { start: 0, end: u64::MAX }],
+        max_bytes: None,
+    };
+    let too_large = NetError::FrameTooLarge {
+        kind: 3,
+        length: u32::MAX,
+        max: FrameLimits::DEFAULT_GET_BLOCKS_MAX,
+    };
+    assert_eq!(huge.ids().err(), Some(too_large.clone()));
+    assert_eq!(encode_get_blocks_for(&CapabilitySet::new(), &huge), Err(too_large));
+
+    // ответчик, получивший такой запрос, тоже не разворачивает его
+    let ranged = CapabilitySet::new().with(Capability::BLOCK_RANGES);
+    let bytes = encode_get_blocks_for(&ranged, &huge).unwrap();
+    assert!(decode_get_blocks_for(&ranged, &bytes).unwrap().ids().is_err());
+
+    let max = GetBlockRanges {
+        selectors: vec![BlockSelector::Range { start: 0, end: GetBlockRanges::MAX_IDS }],
+        max_bytes: None,
+    };
+    assert_eq!(max.ids().unwrap().count() as u64, GetBlockRanges::MAX_IDS);
+}
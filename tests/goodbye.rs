@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use quarxnet::error::NetError;
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    decode_goodbye, encode_goodbye, recv_incoming, send_frame, send_goodbye, FrameDecoder,
+    GoodbyePayload, GoodbyeReason, Incoming, RpcClient, Transport,
+};
+use quarxnet::transport::memory::duplex;
+
+#[test]
+fn goodbye_payload_roundtrip() {
+    let cases = [
+        GoodbyePayload::new(GoodbyeReason::Shutdown),
+        GoodbyePayload::new(GoodbyeReason::Drain)
+            .with_reconnect_after(Duration::from_secs(30))
+            .with_message("rolling restart"),
+        GoodbyePayload::new(GoodbyeReason::Other(900)).with_message("ü"),
+    ];
+    for p in cases {
+        assert_eq!(decode_goodbye(&encode_goodbye(&p)), Ok(p));
+    }
+}
+
+#[test]
+fn goodbye_payload_rejects_malformed() {
+    assert_eq!(decode_goodbye(&[0, 1]), Err(NetError::DecodeError));
+    assert_eq!(decode_goodbye(&[0, 1, 2, 0, 0]), Err(NetError::DecodeError));
+    assert_eq!(decode_goodbye(&[0, 1, 1, 0, 0, 0]), Err(NetError::DecodeError));
+    assert_eq!(decode_goodbye(&[0, 1, 0, 0, 2, b'x']), Err(NetError::DecodeError));
+}
+
+#[test]
+fn recv_incoming_separates_goodbye_from_frames() {
+    let (mut a, mut b) = duplex();
+    let ping = Frame::new(FrameKind::Ping, vec![1]);
+    let bye = GoodbyePayload::new(GoodbyeReason::ProtocolViolation).with_message("bad frame");
+
+    send_frame(&mut a, &ping).unwrap();
+    send_goodbye(&mut a, &bye).unwrap();
+    a.close();
+
+    assert_eq!(recv_incoming(&mut b), Ok(Incoming::Frame(ping)));
+    assert_eq!(recv_incoming(&mut b), Ok(Incoming::Goodbye(bye)));
+    assert_eq!(recv_incoming(&mut b), Err(NetError::ConnectionClosed));
+}
+
+#[test]
+fn decoder_frames_classify_the_same_way() {
+    let (mut a, mut b) = duplex();
+    let bye = GoodbyePayload::new(GoodbyeReason::Idle);
+    send_goodbye(&mut a, &bye).unwrap();
+
+    let bytes = b.recv_exact(6 + encode_goodbye(&bye).len()).unwrap();
+    let frames = FrameDecoder::new().decode(&bytes).unwrap();
+    assert_eq!(frames[0].header.kind, FrameKind::Goodbye);
+    assert_eq!(Incoming::from_frame(frames[0].clone()), Ok(Incoming::Goodbye(bye)));
+}
+
+#[test]
+fn rpc_client_reports_goodbye_to_waiters() {
+    let (a, mut b) = duplex();
+    let mut client = RpcClient::new(a);
+    let id = client.send_request(FrameKind::GetObject, vec![0; 8]).unwrap();
+
+    let bye = GoodbyePayload::new(GoodbyeReason::Drain).with_reconnect_after(Duration::from_millis(1500));
+    send_goodbye(&mut b, &bye).unwrap();
+
+    assert_eq!(client.wait(id), Err(NetError::ConnectionClosed));
+    assert_eq!(client.goodbye(), Some(&bye));
+    // дальше транспорт не читаем
+    assert_eq!(client.wait(id), Err(NetError::ConnectionClosed));
+}
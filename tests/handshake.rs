@@ -0,0 +1,85 @@
+use std::thread;
+
+use quarxnet::error::{ErrorCode, NetError};
+use quarxnet::frame::FrameKind;
+use quarxnet::protocol::{
+    decode_error, handshake, make_error_response, make_frame, recv_frame, send_frame, Session,
+};
+use quarxnet::transport::memory::{duplex, MemoryTransport};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+fn hello(node: u64, major: u16, minor: u16) -> HelloPayload {
+    HelloPayload { node, version: ProtocolVersion { major, minor } }
+}
+
+fn run_pair(
+    a_hello: HelloPayload,
+    b_hello: HelloPayload,
+    notify: bool,
+) -> (Result<Session, NetError>, Result<Session, NetError>, MemoryTransport, MemoryTransport) {
+    let (mut a, mut b) = duplex();
+    let tb = thread::spawn(move || {
+        let r = handshake(&mut b, &b_hello, notify);
+        (r, b)
+    });
+    let ra = handshake(&mut a, &a_hello, notify);
+    let (rb, b) = tb.join().unwrap();
+    (ra, rb, a, b)
+}
+
+#[test]
+fn same_major_picks_lower_minor() {
+    let (ra, rb, _, _) = run_pair(hello(1, 2, 7), hello(2, 2, 3), true);
+    let (sa, sb) = (ra.unwrap(), rb.unwrap());
+
+    assert_eq!(sa.peer_node, 2);
+    assert_eq!(sb.peer_node, 1);
+    for s in [&sa, &sb] {
+        let v = s.version();
+        assert_eq!((v.major, v.minor), (2, 3));
+    }
+}
+
+#[test]
+fn different_major_fails_on_both_sides() {
+    let (ra, rb, _, _) = run_pair(hello(1, 1, 0), hello(2, 2, 0), false);
+    assert_eq!(ra.err(), Some(NetError::IncompatibleVersion { local: 1, remote: 2 }));
+    assert_eq!(rb.err(), Some(NetError::IncompatibleVersion { local: 2, remote: 1 }));
+}
+
+#[test]
+fn mismatch_notifies_peer_with_error_frame() {
+    let (ra, rb, mut a, mut b) = run_pair(hello(1, 1, 0), hello(2, 3, 0), true);
+    assert!(ra.is_err() && rb.is_err());
+
+    for t in [&mut a, &mut b] {
+        let f = recv_frame(t).unwrap();
+        assert_eq!(f.header.kind, FrameKind::Error);
+        let e = decode_error(&f.payload).unwrap();
+        assert_eq!(e.code, ErrorCode::UnsupportedVersion);
+        assert!(e.message.contains("is not supported"));
+    }
+}
+
+#[test]
+fn peer_error_instead_of_hello_is_reported() {
+    let (mut a, mut b) = duplex();
+    let tb = thread::spawn(move || {
+        recv_frame(&mut b).unwrap();
+        let bye = make_error_response(&make_frame(FrameKind::Hello, vec![]), ErrorCode::Busy, "draining");
+        send_frame(&mut b, &bye).unwrap();
+    });
+
+    assert_eq!(
+        handshake(&mut a, &hello(1, 1, 0), true).err(),
+        Some(NetError::Remote { code: ErrorCode::Busy, message: "draining".into() })
+    );
+    tb.join().unwrap();
+}
+
+#[test]
+fn non_hello_reply_is_invalid() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut b, &make_frame(FrameKind::Ping, vec![])).unwrap();
+    assert_eq!(handshake(&mut a, &hello(1, 1, 0), false).err(), Some(NetError::InvalidFrame));
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use quarxnet::error::NetError;
+use quarxnet::frame::FrameKind;
+use quarxnet::health::{Keepalive, KeepaliveConfig, MockClock};
+use quarxnet::protocol::{
+    decode_ping, decode_pong, encode_ping, encode_pong, make_frame, make_response, recv_frame,
+    PingPayload, PongPayload, Transport,
+};
+use quarxnet::transport::memory::{duplex, MemoryTransport};
+
+fn config() -> KeepaliveConfig {
+    KeepaliveConfig {
+        idle: Duration::from_secs(10),
+        pong_timeout: Duration::from_secs(2),
+        max_missed: 3,
+    }
+}
+
+fn setup() -> (Keepalive<MockClock>, MockClock, MemoryTransport, MemoryTransport) {
+    let clock = MockClock::new(1_000_000);
+    let (a, mut b) = duplex();
+    b.set_read_timeout(Some(Duration::from_millis(1)));
+    (Keepalive::with_clock(config(), clock.clone()), clock, a, b)
+}
+
+#[test]
+fn no_ping_before_idle_period() {
+    let (mut ka, clock, mut a, mut b) = setup();
+    clock.advance(Duration::from_secs(9));
+    ka.tick(&mut a).unwrap();
+    assert_eq!(b.recv_exact(1).err(), Some(NetError::Timeout));
+}
+
+#[test]
+fn pong_answers_ping_and_yields_rtt() {
+    let (mut ka, clock, mut a, mut b) = setup();
+    clock.advance(Duration::from_secs(10));
+    ka.tick(&mut a).unwrap();
+
+    let ping_frame = recv_frame(&mut b).unwrap();
+    assert_eq!(ping_frame.header.kind, FrameKind::Ping);
+    let ping = decode_ping(&ping_frame.payload).unwrap();
+    assert_eq!(ping.sent_at_us, 11_000_000);
+
+    clock.advance(Duration::from_millis(40));
+    let pong = make_frame(FrameKind::Pong, encode_pong(&PongPayload::answer(&ping, 0)));
+    assert!(ka.observe(&mut a, &pong).unwrap());
+    assert_eq!(ka.last_rtt().unwrap().rtt, Duration::from_millis(40));
+
+    // после ответа — снова ждём полный idle
+    clock.advance(Duration::from_secs(5));
+    ka.tick(&mut a).unwrap();
+    assert_eq!(b.recv_exact(1).err(), Some(NetError::Timeout));
+}
+
+#[test]
+fn silent_peer_is_declared_dead_after_max_missed() {
+    let (mut ka, clock, mut a, mut b) = setup();
+    clock.advance(Duration::from_secs(10));
+    ka.tick(&mut a).unwrap();
+
+    for missed in 1..3 {
+        clock.advance(Duration::from_secs(2));
+        ka.tick(&mut a).unwrap();
+        assert_eq!(ka.missed(), missed);
+    }
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(ka.tick(&mut a).err(), Some(NetError::PeerDead { missed: 3 }));
+
+    let nonces: Vec<u64> = (0..3)
+        .map(|_| decode_ping(&recv_frame(&mut b).unwrap().payload).unwrap().nonce)
+        .collect();
+    assert_eq!(nonces, vec![1, 2, 3]);
+}
+
+#[test]
+fn any_traffic_counts_as_liveness() {
+    let (mut ka, clock, mut a, mut b) = setup();
+    clock.advance(Duration::from_secs(10));
+    ka.tick(&mut a).unwrap();
+    recv_frame(&mut b).unwrap();
+
+    clock.advance(Duration::from_secs(2));
+    ka.tick(&mut a).unwrap();
+    assert_eq!(ka.missed(), 1);
+
+    let data = make_frame(FrameKind::PushObject, vec![1, 2, 3]);
+    assert!(!ka.observe(&mut a, &data).unwrap());
+    assert_eq!(ka.missed(), 0);
+}
+
+#[test]
+fn stale_pong_does_not_clear_outstanding_ping() {
+    let (mut ka, clock, mut a, mut b) = setup();
+    clock.advance(Duration::from_secs(10));
+    ka.tick(&mut a).unwrap();
+    recv_frame(&mut b).unwrap();
+
+    let stale = PongPayload { nonce: 999, ping_sent_at_us: 0, received_at_us: 0 };
+    assert!(ka.observe(&mut a, &make_frame(FrameKind::Pong, encode_pong(&stale))).unwrap());
+    assert!(ka.last_rtt().is_none());
+
+    clock.advance(Duration::from_secs(2));
+    ka.tick(&mut a).unwrap();
+    assert_eq!(ka.missed(), 1);
+}
+
+#[test]
+fn peer_pings_are_answered() {
+    let (mut ka, clock, mut a, mut b) = setup();
+    let ping = PingPayload { nonce: 5, sent_at_us: 77 };
+    let frame = make_frame(FrameKind::Ping, encode_ping(&ping)).with_request_id(3);
+
+    clock.advance(Duration::from_millis(1));
+    assert!(ka.observe(&mut a, &frame).unwrap());
+
+    let reply = recv_frame(&mut b).unwrap();
+    assert_eq!(reply, make_response(&frame, FrameKind::Pong, encode_pong(&PongPayload::answer(&ping, 1_001_000))));
+    assert_eq!(decode_pong(&reply.payload).unwrap().nonce, 5);
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use quarxnet::error::NetError;
+use quarxnet::health::{LatencyTable, RttSample};
+use quarxnet::protocol::{decode_ping, decode_pong, encode_ping, encode_pong, PingPayload, PongPayload};
+
+#[test]
+fn ping_pong_codecs() {
+    let ping = PingPayload { nonce: 7, sent_at_us: 1_000 };
+    assert_eq!(decode_ping(&encode_ping(&ping)).unwrap(), ping);
+
+    let pong = PongPayload::answer(&ping, 5_000);
+    assert_eq!(pong, PongPayload { nonce: 7, ping_sent_at_us: 1_000, received_at_us: 5_000 });
+    assert_eq!(decode_pong(&encode_pong(&pong)).unwrap(), pong);
+
+    assert_eq!(decode_ping(&[]), Err(NetError::DecodeError));
+    assert_eq!(decode_pong(&encode_ping(&ping)), Err(NetError::DecodeError));
+}
+
+#[test]
+fn rtt_and_offset() {
+    // пинг в t=1000, пир на 500 мкс впереди, путь в одну сторону 100 мкс
+    let pong = PongPayload { nonce: 1, ping_sent_at_us: 1_000, received_at_us: 1_600 };
+    let s = RttSample::from_pong(&pong, 1_200);
+    assert_eq!(s.rtt, Duration::from_micros(200));
+    assert_eq!(s.clock_offset_us, 500);
+
+    let behind = PongPayload { nonce: 1, ping_sent_at_us: 1_000, received_at_us: 100 };
+    assert_eq!(RttSample::from_pong(&behind, 1_200).clock_offset_us, -1_000);
+}
+
+#[test]
+fn table_tracks_peers_and_picks_closest() {
+    let mut t = LatencyTable::new();
+
+    let p1 = t.ping(1, 0);
+    let p2 = t.ping(2, 0);
+    let p3 = t.ping(3, 0);
+    t.record_pong(&PongPayload::answer(&p1, 5), 900).unwrap();
+    t.record_pong(&PongPayload::answer(&p2, 5), 300).unwrap();
+    assert!(t.record_pong(&PongPayload::answer(&p2, 5), 300).is_none(), "duplicate pong");
+
+    assert_eq!(t.get(2).unwrap().srtt, Duration::from_micros(300));
+    assert_eq!(t.closest([1, 2, 3]), Some(2));
+    assert_eq!(t.closest([1, 3]), Some(1));
+    assert_eq!(t.closest([3]), None);
+
+    // один медленный ответ лишь немного сдвигает сглаженный RTT
+    let p2 = t.ping(2, 10_000);
+    t.record_pong(&PongPayload::answer(&p2, 0), 10_000 + 8_300).unwrap();
+    assert_eq!(t.get(2).unwrap().srtt, Duration::from_micros(1_300));
+    assert_eq!(t.get(2).unwrap().samples, 2);
+
+    t.forget(3);
+    assert!(t.record_pong(&PongPayload::answer(&p3, 0), 10).is_none());
+}
+
+#[test]
+fn unanswered_pings_expire() {
+    let mut t = LatencyTable::new().with_pong_timeout(Duration::from_millis(10));
+
+    // пир теряет все пинги: таблица не растёт дальше окна таймаута
+    let lost = t.ping(1, 0);
+    for i in 1..=1_000u64 {
+        t.ping(1, i * 1_000);
+    }
+    assert_eq!(t.outstanding(), 11);
+    assert!(t.record_pong(&PongPayload::answer(&lost, 5), 1_000_000).is_none());
+
+    let late = t.ping(2, 2_000_000);
+    assert!(t.record_pong(&PongPayload::answer(&late, 5), 2_000_000 + 10_001).is_none());
+    let timely = t.ping(2, 3_000_000);
+    assert!(t.record_pong(&PongPayload::answer(&timely, 5), 3_000_000 + 10_000).is_some());
+}
@@ -0,0 +1,94 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use quarxnet::error::NetError;
+use quarxnet::protocol::{recv_frame, send_frame, Transport};
+use quarxnet::transport::memory::duplex;
+use quarxnet::frame::{Frame, FrameHeader, FrameKind};
+
+fn ping(payload: Vec<u8>) -> Frame {
+    Frame {
+        header: FrameHeader::new(FrameKind::Ping, payload.len() as u32),
+        payload: payload.into(),
+    }
+}
+
+#[test]
+fn frames_cross_both_directions() {
+    let (mut a, mut b) = duplex();
+
+    send_frame(&mut a, &ping(vec![1, 2, 3])).unwrap();
+    send_frame(&mut b, &ping(vec![])).unwrap();
+
+    assert_eq!(recv_frame(&mut b).unwrap().payload, vec![1, 2, 3]);
+    assert_eq!(recv_frame(&mut a).unwrap().payload, Vec::<u8>::new());
+}
+
+#[test]
+fn short_reads_are_reassembled() {
+    let (mut a, mut b) = duplex();
+    b.script_short_reads([1, 1, 2, 1, 3, 1]);
+
+    send_frame(&mut a, &ping((0..50).collect())).unwrap();
+    send_frame(&mut a, &ping(vec![9])).unwrap();
+
+    assert_eq!(recv_frame(&mut b).unwrap().payload, (0..50).collect::<Vec<u8>>());
+    assert_eq!(recv_frame(&mut b).unwrap().payload, vec![9]);
+}
+
+#[test]
+fn peer_closing_mid_frame_fails_recv() {
+    let (mut a, mut b) = duplex();
+    b.script_short_reads([4, 4]);
+
+    // заголовок обещает 10 байт payload, приходит 4
+    a.send(&[7, 0, 0, 0, 0, 10, 1, 2, 3, 4]).unwrap();
+    a.close();
+
+    assert_eq!(recv_frame(&mut b).err(), Some(NetError::ConnectionClosed));
+    assert_eq!(a.send(&[0]).err(), Some(NetError::ConnectionClosed));
+}
+
+#[test]
+fn dropping_an_endpoint_closes_the_peer() {
+    let (a, mut b) = duplex();
+    drop(a);
+
+    assert_eq!(b.recv_exact(1).err(), Some(NetError::ConnectionClosed));
+    assert_eq!(b.send(&[1]).err(), Some(NetError::ConnectionClosed));
+}
+
+#[test]
+fn injected_errors_fire_once() {
+    let (mut a, mut b) = duplex();
+    a.fail_next_send(NetError::Timeout);
+    b.fail_next_recv(NetError::Io(std::io::ErrorKind::Other));
+
+    assert_eq!(send_frame(&mut a, &ping(vec![5])).err(), Some(NetError::Timeout));
+    send_frame(&mut a, &ping(vec![6])).unwrap();
+
+    assert_eq!(recv_frame(&mut b).err(), Some(NetError::Io(std::io::ErrorKind::Other)));
+    assert_eq!(recv_frame(&mut b).unwrap().payload, vec![6]);
+}
+
+#[test]
+fn read_timeout() {
+    let (_a, mut b) = duplex();
+    b.set_read_timeout(Some(Duration::from_millis(10)));
+    assert_eq!(b.recv_exact(1).err(), Some(NetError::Timeout));
+}
+
+#[test]
+fn io_read_write_honours_short_reads() {
+    let (mut a, mut b) = duplex();
+    b.script_short_reads([2]);
+
+    a.write_all(b"hello").unwrap();
+    a.close();
+
+    let mut buf = [0u8; 8];
+    assert_eq!(b.read(&mut buf).unwrap(), 2);
+    assert_eq!(b.read(&mut buf[2..]).unwrap(), 3);
+    assert_eq!(&buf[..5], b"hello");
+    assert_eq!(b.read(&mut buf).unwrap(), 0);
+}
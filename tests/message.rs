@@ -0,0 +1,66 @@
+use quarxtor_core::net_core::{
+    GetBlocksPayload, GetObjectPayload, HelloPayload, ProtocolVersion, PushObjectPayload,
+};
+
+use quarxnet::capability::{Capability, CapabilitySet};
+use quarxnet::error::NetError;
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::message::{recv_message, send_message, Message};
+use quarxnet::protocol::{encode_get_object, send_frame, GoodbyePayload, GoodbyeReason, PingPayload};
+use quarxnet::transport::memory::duplex;
+
+#[test]
+fn encode_sets_kind_and_payload() {
+    let m = Message::GetObject(GetObjectPayload { id: 7 });
+    let f = m.encode();
+    assert_eq!(f.header.kind, FrameKind::GetObject);
+    assert_eq!(f.payload, encode_get_object(&GetObjectPayload { id: 7 }));
+    assert_eq!(m.kind(), FrameKind::GetObject);
+}
+
+#[test]
+fn messages_roundtrip_through_frames() {
+    let caps = CapabilitySet::new().with(Capability::CRC32C).with_extension("x-test").unwrap();
+    let ping = PingPayload { nonce: 3, sent_at_us: 44 };
+    let bye = GoodbyePayload::new(GoodbyeReason::Shutdown).with_message("bye");
+
+    let messages = [
+        Message::Caps(caps),
+        Message::Ping(ping),
+        Message::Goodbye(bye),
+        Message::PushObject(PushObjectPayload { raw: vec![1, 2, 3] }),
+        Message::GetBlocks(GetBlocksPayload { ids: vec![4, 5] }),
+    ];
+    for m in messages {
+        assert_eq!(Message::decode(m.encode()).unwrap(), m);
+    }
+}
+
+#[test]
+fn debug_shows_payload_sizes_not_data() {
+    let m = Message::PushObject(PushObjectPayload { raw: vec![0xAB; 4096] });
+    assert_eq!(format!("{m:?}"), "PushObject { len: 4096 }");
+    assert_eq!(m.clone(), m);
+    assert_ne!(m, Message::PushObject(PushObjectPayload { raw: vec![] }));
+}
+
+#[test]
+fn decode_uses_the_codec_of_the_frame_kind() {
+    // 8-байтный payload подходит GetObject, но не Ping
+    let f = Frame::new(FrameKind::Ping, 7u64.to_be_bytes().to_vec());
+    assert!(matches!(Message::decode(f), Err(NetError::DecodeError)));
+}
+
+#[test]
+fn send_and_recv_message_over_transport() {
+    let (mut a, mut b) = duplex();
+    let version = ProtocolVersion { major: 1, minor: 2 };
+    let hello = Message::Hello(HelloPayload { node: 42, version });
+    send_message(&mut a, &hello).unwrap();
+    assert_eq!(recv_message(&mut b).unwrap(), hello);
+
+    send_frame(&mut a, &Frame::new(FrameKind::Hello, vec![0; 3])).unwrap();
+    assert!(matches!(recv_message(&mut b), Err(NetError::DecodeError)));
+    a.close();
+    assert!(matches!(recv_message(&mut b), Err(NetError::ConnectionClosed)));
+}
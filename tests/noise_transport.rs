@@ -0,0 +1,147 @@
+#![cfg(feature = "noise")]
+
+use std::thread;
+
+use quarxnet::error::{NetError, NetResult};
+use quarxnet::frame::{FrameHeader, FrameKind};
+use quarxnet::protocol::{
+    handshake, make_frame, recv_frame, send_frame, send_frame_vectored, FrameOptions, Transport,
+};
+use quarxnet::transport::memory::{duplex, MemoryTransport};
+use quarxnet::transport::{NoiseKeypair, NoiseTransport};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+type Noise = NoiseTransport<MemoryTransport>;
+
+fn hello(node: u64) -> HelloPayload {
+    HelloPayload { node, version: ProtocolVersion { major: 1, minor: 0 } }
+}
+
+/// Runs the responder on a thread; returns (initiator, responder).
+fn connect<F>(server_keys: NoiseKeypair, initiate: F) -> (NetResult<Noise>, NetResult<Noise>)
+where
+    F: FnOnce(MemoryTransport) -> NetResult<Noise>,
+{
+    let (a, b) = duplex();
+    let server = thread::spawn(move || NoiseTransport::respond(b, &server_keys));
+    let client = initiate(a);
+    (client, server.join().unwrap())
+}
+
+#[test]
+fn xx_binds_node_ids_to_static_keys() {
+    let server_keys = NoiseKeypair::generate().unwrap();
+    let client_keys = NoiseKeypair::generate().unwrap();
+    let (sk, ck) = (server_keys.clone(), client_keys.clone());
+
+    let (c, s) = connect(server_keys, |t| NoiseTransport::initiate_xx(t, &ck));
+    let (mut c, mut s) = (c.unwrap(), s.unwrap());
+    assert_eq!(c.peer_static_key(), sk.public_key());
+    assert_eq!(s.peer_static_key(), client_keys.public_key());
+
+    let server = thread::spawn(move || {
+        let session = handshake(&mut s, &hello(sk.node_id()), false).unwrap();
+        s.check_node(session.peer_node).unwrap();
+        assert_eq!(
+            s.check_node(session.peer_node ^ 1),
+            Err(NetError::PeerIdentityMismatch { node: session.peer_node ^ 1 })
+        );
+        let f = recv_frame(&mut s).unwrap();
+        send_frame(&mut s, &make_frame(FrameKind::PushObject, f.payload.to_vec())).unwrap();
+    });
+
+    let session = handshake(&mut c, &hello(client_keys.node_id()), false).unwrap();
+    assert_eq!(session.peer_node, c.peer_node_id());
+
+    // больше одной записи Noise
+    let big: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+    send_frame(&mut c, &make_frame(FrameKind::PushObject, big.clone())).unwrap();
+    assert_eq!(recv_frame(&mut c).unwrap().payload, big);
+    server.join().unwrap();
+}
+
+#[test]
+fn ik_with_known_responder_key() {
+    let server_keys = NoiseKeypair::generate().unwrap();
+    let client_keys = NoiseKeypair::generate().unwrap();
+    let remote = *server_keys.public_key();
+
+    let (c, s) = connect(server_keys, |t| NoiseTransport::initiate_ik(t, &client_keys, &remote));
+    let (mut c, mut s) = (c.unwrap(), s.unwrap());
+    assert_eq!(s.peer_node_id(), client_keys.node_id());
+
+    send_frame(&mut c, &make_frame(FrameKind::Ping, vec![1, 2])).unwrap();
+    assert_eq!(recv_frame(&mut s).unwrap().payload, vec![1, 2]);
+}
+
+#[test]
+fn ik_with_wrong_responder_key_fails() {
+    let server_keys = NoiseKeypair::generate().unwrap();
+    let client_keys = NoiseKeypair::generate().unwrap();
+    let wrong = *NoiseKeypair::generate().unwrap().public_key();
+
+    let (a, b) = duplex();
+    let server = thread::spawn(move || NoiseTransport::respond(b, &server_keys).err());
+    let client = NoiseTransport::initiate_ik(a, &client_keys, &wrong);
+
+    assert!(matches!(server.join().unwrap(), Some(NetError::Noise(_))));
+    assert!(client.is_err());
+}
+
+#[test]
+fn vectored_frames_roundtrip() {
+    let keys = NoiseKeypair::generate().unwrap();
+    let ck = NoiseKeypair::generate().unwrap();
+    let (c, s) = connect(keys, |t| NoiseTransport::initiate_xx(t, &ck));
+    let (mut c, mut s) = (c.unwrap(), s.unwrap());
+    let opts = FrameOptions::default();
+
+    // крупная часть шифруется прямо из среза, мелкие делят записи
+    let big: Vec<u8> = (0..150_000).map(|i| (i % 251) as u8).collect();
+    let header = FrameHeader::new(FrameKind::PushObject, 0);
+    send_frame_vectored(&mut c, &header, &big, &opts).unwrap();
+    for i in 0..100u8 {
+        send_frame_vectored(&mut c, &FrameHeader::new(FrameKind::Ping, 0), &[i; 3], &opts).unwrap();
+    }
+
+    assert_eq!(recv_frame(&mut s).unwrap().payload, big);
+    for i in 0..100u8 {
+        assert_eq!(recv_frame(&mut s).unwrap().payload, vec![i; 3]);
+    }
+    c.send_vectored(&[b"ab", &[], b"cd"]).unwrap();
+    assert_eq!(s.recv_exact(4).unwrap(), b"abcd");
+}
+
+#[test]
+fn forged_record_is_rejected() {
+    let keys = NoiseKeypair::generate().unwrap();
+    let ck = NoiseKeypair::generate().unwrap();
+    let (c, s) = connect(keys, |t| NoiseTransport::initiate_xx(t, &ck));
+    let (mut c, mut s) = (c.unwrap(), s.unwrap());
+
+    let mut record = vec![0, 22];
+    record.extend_from_slice(&[0xAB; 22]);
+    c.get_mut().send(&record).unwrap();
+    assert!(matches!(recv_frame(&mut s), Err(NetError::Noise(_))));
+}
+
+#[test]
+fn keypair_restores_from_saved_keys() {
+    let keys = NoiseKeypair::generate().unwrap();
+    let restored = NoiseKeypair::from_keys(keys.private_key(), keys.public_key()).unwrap();
+    assert_eq!(restored.node_id(), keys.node_id());
+    assert!(matches!(NoiseKeypair::from_keys(&[0; 31], &[0; 32]), Err(NetError::Noise(_))));
+
+    let derived = NoiseKeypair::from_private_key(keys.private_key()).unwrap();
+    assert_eq!(derived.public_key(), keys.public_key());
+}
+
+#[test]
+fn mismatched_public_key_is_rejected() {
+    let keys = NoiseKeypair::generate().unwrap();
+    let other = NoiseKeypair::generate().unwrap();
+    assert!(matches!(
+        NoiseKeypair::from_keys(keys.private_key(), other.public_key()),
+        Err(NetError::Noise(_))
+    ));
+}
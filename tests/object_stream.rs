@@ -0,0 +1,167 @@
+use std::io::Cursor;
+
+use quarxnet::error::NetError;
+use quarxnet::frame::FrameKind;
+use quarxnet::protocol::{
+    make_frame, make_response, recv_frame, send_frame, FrameLimits, RpcClient, FLAG_MORE,
+    FLAG_STREAM,
+};
+use quarxnet::stream::{
+    recv_object_stream, send_object_stream, ObjectStreamReceiver, StreamOptions, StreamProgress,
+};
+use quarxnet::transport::memory::duplex;
+
+fn object(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+#[test]
+fn large_object_roundtrip_in_bounded_chunks() {
+    let data = object(5 * 1024 * 1024 + 17);
+    let (mut a, mut b) = duplex();
+    let opts = StreamOptions { chunk_size: 256 * 1024, ..StreamOptions::default() };
+
+    let hint = Some(data.len() as u64);
+    let sent = send_object_stream(&mut a, 42, hint, &mut Cursor::new(&data), &opts).unwrap();
+
+    let mut out = Vec::new();
+    let got = recv_object_stream(&mut b, &mut out, &FrameLimits::uniform(256 * 1024)).unwrap();
+    assert_eq!(got, sent);
+    assert_eq!(got.id, 42);
+    assert_eq!(got.length, data.len() as u64);
+    assert_eq!(got.hash, *blake3::hash(&data).as_bytes());
+    assert_eq!(out, data);
+}
+
+#[test]
+fn frame_layout() {
+    let (mut a, mut b) = duplex();
+    let opts = StreamOptions { chunk_size: 4, request_id: Some(9), ..StreamOptions::default() };
+    send_object_stream(&mut a, 1, None, &mut Cursor::new(b"abcdefghij"), &opts).unwrap();
+
+    let frames: Vec<_> = (0..5).map(|_| recv_frame(&mut b).unwrap()).collect();
+    let flags: Vec<u8> = frames.iter().map(|f| f.header.flags).collect();
+    let more = FLAG_STREAM | FLAG_MORE;
+    assert_eq!(flags, vec![more, more, more, more, FLAG_STREAM]);
+    assert!(frames.iter().all(|f| f.header.kind == FrameKind::PushObject));
+    assert!(frames.iter().all(|f| f.header.request_id == Some(9)));
+    assert_eq!(&frames[0].payload[8..], &u64::MAX.to_be_bytes());
+    assert_eq!(frames[3].payload, &b"ij"[..]);
+}
+
+#[test]
+fn empty_object() {
+    let (mut a, mut b) = duplex();
+    send_object_stream(&mut a, 7, Some(0), &mut Cursor::new(b""), &StreamOptions::default()).unwrap();
+
+    let mut rx = ObjectStreamReceiver::new(Vec::new());
+    assert_eq!(rx.accept(&recv_frame(&mut b).unwrap()).unwrap(), StreamProgress::Continue);
+    assert_eq!(rx.current(), Some((7, Some(0))));
+    match rx.accept(&recv_frame(&mut b).unwrap()).unwrap() {
+        StreamProgress::Done(s) => assert_eq!((s.id, s.length), (7, 0)),
+        other => panic!("unexpected {other:?}"),
+    }
+    assert!(rx.into_inner().is_empty());
+}
+
+#[test]
+fn tampered_chunk_fails_integrity_check() {
+    let (mut a, mut b) = duplex();
+    let opts = StreamOptions { chunk_size: 8, ..StreamOptions::default() };
+    send_object_stream(&mut a, 5, None, &mut Cursor::new(object(40)), &opts).unwrap();
+
+    let mut rx = ObjectStreamReceiver::new(Vec::new());
+    rx.accept(&recv_frame(&mut b).unwrap()).unwrap();
+    let mut chunk = recv_frame(&mut b).unwrap();
+    let mut tampered = chunk.payload.to_vec();
+    tampered[0] ^= 0xFF;
+    chunk.payload = tampered.into();
+    rx.accept(&chunk).unwrap();
+
+    let mut last = StreamProgress::Continue;
+    for _ in 0..5 {
+        match rx.accept(&recv_frame(&mut b).unwrap()) {
+            Ok(p) => last = p,
+            Err(e) => {
+                assert_eq!(e, NetError::ObjectIntegrity { id: 5 });
+                return;
+            }
+        }
+    }
+    panic!("stream accepted: {last:?}");
+}
+
+#[test]
+fn unrelated_frame_mid_stream_is_rejected() {
+    let (mut a, mut b) = duplex();
+    let opts = StreamOptions { chunk_size: 8, ..StreamOptions::default() };
+    send_frame(&mut a, &make_frame(FrameKind::PushObject, vec![0; 16])).unwrap();
+    send_object_stream(&mut a, 5, None, &mut Cursor::new(object(4)), &opts).unwrap();
+
+    let mut out = Vec::new();
+    assert_eq!(
+        recv_object_stream(&mut b, &mut out, &FrameLimits::default()).err(),
+        Some(NetError::InvalidFrame)
+    );
+}
+
+#[test]
+fn streamed_response_through_rpc_client() {
+    let data = object(300 * 1024 + 5);
+    let expected = data.clone();
+    let (a, mut b) = duplex();
+
+    let server = std::thread::spawn(move || {
+        let get = recv_frame(&mut b).unwrap();
+        let ping = recv_frame(&mut b).unwrap();
+        let opts = StreamOptions {
+            chunk_size: 64 * 1024,
+            request_id: get.header.request_id,
+            ..StreamOptions::default()
+        };
+        send_object_stream(&mut b, 5, None, &mut Cursor::new(&data), &opts).unwrap();
+        send_frame(&mut b, &make_response(&ping, FrameKind::Pong, vec![0; 16])).unwrap();
+    });
+
+    let mut client = RpcClient::new(a);
+    let get = client.send_request(FrameKind::GetObject, vec![0; 8]).unwrap();
+    let ping = client.send_request(FrameKind::Ping, vec![0; 8]).unwrap();
+
+    // ответ на Ping идёт после потока: поток нельзя пропустить, копя его кадры
+    assert_eq!(client.wait(ping).err(), Some(NetError::StreamPending { request_id: get }));
+
+    let mut out = Vec::new();
+    let summary = client.wait_stream(get, &mut out).unwrap();
+    assert_eq!((summary.id, summary.length), (5, expected.len() as u64));
+    assert_eq!(out, expected);
+    assert_eq!(client.wait(ping).unwrap().header.kind, FrameKind::Pong);
+    assert_eq!(client.in_flight(), 0);
+    server.join().unwrap();
+}
+
+#[test]
+fn get_objects_reads_streamed_responses_whole() {
+    let data = object(200 * 1024);
+    let expected = data.clone();
+    let (a, mut b) = duplex();
+
+    // второй объект приходит потоком и раньше первого
+    let server = std::thread::spawn(move || {
+        let first = recv_frame(&mut b).unwrap();
+        let second = recv_frame(&mut b).unwrap();
+        let opts = StreamOptions {
+            chunk_size: 64 * 1024,
+            request_id: second.header.request_id,
+            ..StreamOptions::default()
+        };
+        send_object_stream(&mut b, 2, None, &mut Cursor::new(&data), &opts).unwrap();
+        send_frame(&mut b, &make_response(&first, FrameKind::PushObject, vec![1, 2, 3])).unwrap();
+    });
+
+    let mut client = RpcClient::new(a);
+    let objs = client.get_objects(&[1, 2]).unwrap();
+    assert_eq!(objs[0].raw, vec![1, 2, 3]);
+    assert_eq!(objs[1].raw, expected);
+    assert_eq!(client.in_flight(), 0);
+    server.join().unwrap();
+}
@@ -0,0 +1,73 @@
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    decode_push_object_shared, make_response, recv_frame, recv_frame_pooled, send_frame,
+    send_frame_with, FrameLimits, FrameOptions, RecvBuffer, RpcClient,
+};
+use quarxnet::transport::memory::duplex;
+use quarxtor_core::net_core::PushObjectPayload;
+
+#[test]
+fn pooled_receive_matches_plain_receive() {
+    let frame = Frame::new(FrameKind::PushObject, vec![3; 1000]).with_request_id(9);
+    let opts = FrameOptions { checksum: true, ..FrameOptions::default() };
+    let (mut a, mut b) = duplex();
+    send_frame_with(&mut a, &frame, &opts).unwrap();
+    send_frame_with(&mut a, &frame, &opts).unwrap();
+
+    let mut buf = RecvBuffer::new();
+    let pooled = recv_frame_pooled(&mut b, &mut buf, &FrameLimits::default()).unwrap();
+    assert_eq!(pooled, recv_frame(&mut b).unwrap());
+    assert_eq!(pooled, frame);
+}
+
+#[test]
+fn consecutive_payloads_share_one_allocation() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Frame::new(FrameKind::Ping, vec![1; 10])).unwrap();
+    send_frame(&mut a, &Frame::new(FrameKind::Ping, vec![2; 20])).unwrap();
+
+    let mut buf = RecvBuffer::new();
+    let limits = FrameLimits::default();
+    let first = recv_frame_pooled(&mut b, &mut buf, &limits).unwrap();
+    let second = recv_frame_pooled(&mut b, &mut buf, &limits).unwrap();
+
+    assert_eq!(second.payload.as_ptr(), first.payload.as_ptr().wrapping_add(10));
+    assert_eq!(first.payload, vec![1; 10]);
+    assert_eq!(second.payload, vec![2; 20]);
+}
+
+#[test]
+fn large_bodies_get_their_own_block() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Frame::new(FrameKind::PushObject, vec![7; 300])).unwrap();
+
+    let mut buf = RecvBuffer::with_block_size(64);
+    let f = recv_frame_pooled(&mut b, &mut buf, &FrameLimits::default()).unwrap();
+    assert_eq!(f.payload, vec![7; 300]);
+}
+
+#[test]
+fn shared_push_object_does_not_copy() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &Frame::new(FrameKind::PushObject, vec![5; 4096])).unwrap();
+
+    let f = recv_frame_pooled(&mut b, &mut RecvBuffer::new(), &FrameLimits::default()).unwrap();
+    let obj = decode_push_object_shared(&f.payload).unwrap();
+    assert_eq!(obj.raw.as_ptr(), f.payload.as_ptr());
+
+    let owned: PushObjectPayload = obj.into();
+    assert_eq!(owned.raw, vec![5; 4096]);
+}
+
+#[test]
+fn rpc_client_receives_through_pool() {
+    let (a, mut b) = duplex();
+    let mut client = RpcClient::new(a);
+    let id = client.send_request(FrameKind::GetObject, vec![0; 8]).unwrap();
+
+    let req = recv_frame(&mut b).unwrap();
+    let resp = make_response(&req, FrameKind::PushObject, vec![8; 100]);
+    send_frame(&mut b, &resp).unwrap();
+
+    assert_eq!(client.wait(id).unwrap(), resp);
+}
@@ -0,0 +1,143 @@
+use std::thread;
+
+use quarxnet::error::{ErrorCode, NetError};
+use quarxnet::frame::{Frame, FrameKind};
+use quarxnet::protocol::{
+    decode_get_object, encode_push_object, make_error_response, make_frame, make_response,
+    recv_frame, send_frame, RpcClient, Transport, FLAG_REQUEST_ID,
+};
+use quarxnet::transport::memory::duplex;
+use quarxtor_core::net_core::PushObjectPayload;
+
+#[test]
+fn request_id_wire_layout_and_roundtrip() {
+    let (mut a, mut b) = duplex();
+    let f = Frame::new(FrameKind::GetObject, vec![0, 0, 0, 0, 0, 0, 0, 9]).with_request_id(0x0102_0304);
+
+    send_frame(&mut a, &f).unwrap();
+    let wire = b.recv_exact(6 + 4 + 8).unwrap();
+    assert_eq!(&wire[..10], &[5, FLAG_REQUEST_ID, 0, 0, 0, 8, 1, 2, 3, 4]);
+
+    send_frame(&mut a, &f).unwrap();
+    assert_eq!(recv_frame(&mut b).unwrap(), f);
+}
+
+#[test]
+fn frames_without_request_id_are_unchanged_on_the_wire() {
+    let (mut a, mut b) = duplex();
+    send_frame(&mut a, &make_frame(FrameKind::Ping, vec![1])).unwrap();
+    assert_eq!(b.recv_exact(7).unwrap(), vec![7, 0, 0, 0, 0, 1, 1]);
+}
+
+/// Answers GetObject requests in reverse order of arrival, with payload =
+/// the requested id as text.
+fn reversing_server<T: Transport>(mut t: T, n: usize) {
+    let mut reqs = Vec::new();
+    for _ in 0..n {
+        reqs.push(recv_frame(&mut t).unwrap());
+    }
+    for req in reqs.iter().rev() {
+        let id = decode_get_object(&req.payload).unwrap().id;
+        let body = encode_push_object(&PushObjectPayload { raw: id.to_string().into_bytes() });
+        send_frame(&mut t, &make_response(req, FrameKind::PushObject, body)).unwrap();
+    }
+}
+
+#[test]
+fn pipelined_get_objects_routes_out_of_order_responses() {
+    let (a, b) = duplex();
+    let server = thread::spawn(move || reversing_server(b, 5));
+
+    let mut client = RpcClient::new(a);
+    let objs = client.get_objects(&[10, 20, 30, 40, 50]).unwrap();
+    let texts: Vec<String> = objs.into_iter().map(|o| String::from_utf8(o.raw).unwrap()).collect();
+    assert_eq!(texts, ["10", "20", "30", "40", "50"]);
+    assert_eq!(client.in_flight(), 0);
+
+    server.join().unwrap();
+}
+
+#[test]
+fn each_waiter_gets_its_own_response() {
+    let (a, b) = duplex();
+    let server = thread::spawn(move || reversing_server(b, 3));
+
+    let mut client = RpcClient::new(a);
+    let r1 = client.send_request(FrameKind::GetObject, 1u64.to_be_bytes().to_vec()).unwrap();
+    let r2 = client.send_request(FrameKind::GetObject, 2u64.to_be_bytes().to_vec()).unwrap();
+    let r3 = client.send_request(FrameKind::GetObject, 3u64.to_be_bytes().to_vec()).unwrap();
+    assert_eq!(client.in_flight(), 3);
+
+    assert_eq!(client.wait(r2).unwrap().payload, &b"2"[..]);
+    assert_eq!(client.wait(r1).unwrap().payload, &b"1"[..]);
+    assert_eq!(client.wait(r3).unwrap().payload, &b"3"[..]);
+    assert_eq!(client.wait(r3).err(), Some(NetError::InvalidFrame));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn unsolicited_frames_are_queued() {
+    let (a, mut b) = duplex();
+    let mut client = RpcClient::new(a);
+    let rid = client.send_request(FrameKind::GetObject, 7u64.to_be_bytes().to_vec()).unwrap();
+
+    let req = recv_frame(&mut b).unwrap();
+    send_frame(&mut b, &make_frame(FrameKind::Ping, vec![])).unwrap();
+    send_frame(&mut b, &make_response(&req, FrameKind::PushObject, vec![7])).unwrap();
+
+    assert_eq!(client.wait(rid).unwrap().payload, vec![7]);
+    assert_eq!(client.take_unsolicited().unwrap().header.kind, FrameKind::Ping);
+    assert!(client.take_unsolicited().is_none());
+}
+
+#[test]
+fn response_to_unknown_request_is_rejected() {
+    let (a, mut b) = duplex();
+    let mut client = RpcClient::new(a);
+    let rid = client.send_request(FrameKind::GetObject, vec![0; 8]).unwrap();
+
+    send_frame(&mut b, &Frame::new(FrameKind::PushObject, vec![]).with_request_id(rid + 100)).unwrap();
+    assert_eq!(client.wait(rid).err(), Some(NetError::InvalidFrame));
+}
+
+#[test]
+fn failed_get_objects_drops_the_rest() {
+    let (a, mut b) = duplex();
+    let server = thread::spawn(move || {
+        let reqs: Vec<Frame> = (0..3).map(|_| recv_frame(&mut b).unwrap()).collect();
+        let missing = make_error_response(&reqs[0], ErrorCode::NotFound, "no such object");
+        send_frame(&mut b, &missing).unwrap();
+        for req in &reqs[1..] {
+            send_frame(&mut b, &make_response(req, FrameKind::PushObject, vec![1])).unwrap();
+        }
+        let ping = recv_frame(&mut b).unwrap();
+        send_frame(&mut b, &make_response(&ping, FrameKind::Pong, vec![])).unwrap();
+    });
+
+    let mut client = RpcClient::new(a);
+    assert!(matches!(client.get_objects(&[1, 2, 3]), Err(NetError::Remote { .. })));
+    assert_eq!(client.in_flight(), 0);
+
+    // поздние ответы 2 и 3 отбрасываются, очередь не растёт
+    assert_eq!(client.call(FrameKind::Ping, vec![]).unwrap().header.kind, FrameKind::Pong);
+    assert!(client.take_unsolicited().is_none());
+    server.join().unwrap();
+}
+
+#[test]
+fn cancelled_request_response_is_discarded() {
+    let (a, mut b) = duplex();
+    let mut client = RpcClient::new(a);
+    let r1 = client.send_request(FrameKind::GetObject, vec![0; 8]).unwrap();
+    let r2 = client.send_request(FrameKind::GetObject, vec![0; 8]).unwrap();
+    client.cancel(r1);
+    assert_eq!(client.in_flight(), 1);
+    assert_eq!(client.wait(r1).err(), Some(NetError::InvalidFrame));
+
+    let req1 = recv_frame(&mut b).unwrap();
+    let req2 = recv_frame(&mut b).unwrap();
+    send_frame(&mut b, &make_response(&req1, FrameKind::PushObject, vec![1])).unwrap();
+    send_frame(&mut b, &make_response(&req2, FrameKind::PushObject, vec![2])).unwrap();
+    assert_eq!(client.wait(r2).unwrap().payload, vec![2]);
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "identity")]
+
+use std::thread;
+
+use quarxnet::error::{ErrorCode, NetError, NetResult};
+use quarxnet::frame::FrameKind;
+use quarxnet::identity::{
+    decode_signed_hello, encode_signed_hello, node_id, signed_handshake, NodeKeypair, SignedHello,
+};
+use quarxnet::protocol::{
+    decode_error, decode_hello, encode_auth, handshake, make_frame, recv_frame, send_frame,
+    AuthMethod, AuthPayload, Session,
+};
+use quarxnet::transport::memory::{duplex, MemoryTransport};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+const V1: ProtocolVersion = ProtocolVersion { major: 1, minor: 0 };
+
+/// Runs `peer` on a thread against a signed handshake with `keys`.
+fn against<F>(keys: NodeKeypair, peer: F) -> (NetResult<Session>, MemoryTransport)
+where
+    F: FnOnce(&mut MemoryTransport) + Send + 'static,
+{
+    let (mut a, mut b) = duplex();
+    let tb = thread::spawn(move || {
+        peer(&mut b);
+        b
+    });
+    let r = signed_handshake(&mut a, &keys, V1, true);
+    (r, tb.join().unwrap())
+}
+
+fn expect_unauthorized(t: &mut MemoryTransport) {
+    let f = recv_frame(t).unwrap();
+    assert_eq!(f.header.kind, FrameKind::Error);
+    assert_eq!(decode_error(&f.payload).unwrap().code, ErrorCode::Unauthorized);
+}
+
+#[test]
+fn signed_hello_round_trips_and_reads_as_plain_hello() {
+    let keys = NodeKeypair::generate().unwrap();
+    let hello = SignedHello::new(&keys, V1).unwrap();
+    assert_eq!(hello.hello.node, node_id(&keys.public_key()));
+
+    let b = encode_signed_hello(&hello);
+    let back = decode_signed_hello(&b).unwrap();
+    assert_eq!(back.hello.node, hello.hello.node);
+    assert_eq!((back.public_key, back.nonce), (hello.public_key, hello.nonce));
+    assert_eq!(decode_hello(&b).unwrap().node, hello.hello.node);
+    assert!(matches!(decode_signed_hello(&b[..12]), Err(NetError::DecodeError)));
+}
+
+#[test]
+fn keypair_restores_from_secret() {
+    let keys = NodeKeypair::generate().unwrap();
+    let restored = NodeKeypair::from_secret_key(&keys.secret_key());
+    assert_eq!(restored.public_key(), keys.public_key());
+    assert_eq!(restored.node_id(), keys.node_id());
+    assert!(!format!("{keys:?}").contains(&format!("{:?}", keys.secret_key())));
+}
+
+#[test]
+fn both_sides_verify_each_other() {
+    let (ka, kb) = (NodeKeypair::generate().unwrap(), NodeKeypair::generate().unwrap());
+    let (pa, pb) = (ka.public_key(), kb.public_key());
+
+    let (mut a, mut b) = duplex();
+    let tb = thread::spawn(move || signed_handshake(&mut b, &kb, V1, true));
+    let sa = signed_handshake(&mut a, &ka, V1, true).unwrap();
+    let sb = tb.join().unwrap().unwrap();
+
+    assert_eq!(sa.peer_node, node_id(&pb));
+    assert_eq!(sa.peer_public_key(), Some(&pb));
+    assert_eq!(sb.peer_node, node_id(&pa));
+    assert_eq!(sb.peer_public_key(), Some(&pa));
+}
+
+#[test]
+fn plain_handshake_has_no_peer_key() {
+    let (mut a, mut b) = duplex();
+    let hello = |node| HelloPayload { node, version: V1 };
+    let tb = thread::spawn(move || handshake(&mut b, &hello(2), false));
+    let sa = handshake(&mut a, &hello(1), false).unwrap();
+    assert_eq!(sa.peer_public_key(), None);
+    assert!(tb.join().unwrap().is_ok());
+}
+
+#[test]
+fn plain_hello_peer_is_refused() {
+    let keys = NodeKeypair::generate().unwrap();
+    let (r, mut b) = against(keys, |b| {
+        // старый узел видит обычный Hello и считает рукопожатие успешным
+        handshake(b, &HelloPayload { node: 7, version: V1 }, false).unwrap();
+    });
+
+    assert_eq!(r, Err(NetError::PeerIdentityMismatch { node: 7 }));
+    expect_unauthorized(&mut b);
+}
+
+#[test]
+fn node_id_not_derived_from_key_is_refused() {
+    let keys = NodeKeypair::generate().unwrap();
+    let (r, mut b) = against(keys, |b| {
+        let mut forged = SignedHello::new(&NodeKeypair::generate().unwrap(), V1).unwrap();
+        forged.hello.node = 42;
+        send_frame(b, &make_frame(FrameKind::Hello, encode_signed_hello(&forged))).unwrap();
+    });
+
+    assert_eq!(r, Err(NetError::PeerIdentityMismatch { node: 42 }));
+    let _our_hello = recv_frame(&mut b).unwrap();
+    expect_unauthorized(&mut b);
+}
+
+#[test]
+fn stolen_public_key_without_secret_is_refused() {
+    let keys = NodeKeypair::generate().unwrap();
+    let victim = NodeKeypair::generate().unwrap().public_key();
+    let (r, mut b) = against(keys, move |b| {
+        let mut forged = SignedHello::new(&NodeKeypair::generate().unwrap(), V1).unwrap();
+        forged.public_key = victim;
+        forged.hello.node = node_id(&victim);
+        send_frame(b, &make_frame(FrameKind::Hello, encode_signed_hello(&forged))).unwrap();
+        let proof = AuthPayload { method: AuthMethod::Signature, data: vec![0; 64] };
+        send_frame(b, &make_frame(FrameKind::Auth, encode_auth(&proof))).unwrap();
+    });
+
+    assert_eq!(r, Err(NetError::PeerIdentityMismatch { node: node_id(&victim) }));
+    let _our_hello = recv_frame(&mut b).unwrap();
+    let our_proof = recv_frame(&mut b).unwrap();
+    assert_eq!(our_proof.header.kind, FrameKind::Auth);
+    expect_unauthorized(&mut b);
+}
+
+#[test]
+fn wrong_signature_is_refused() {
+    let (ka, kb) = (NodeKeypair::generate().unwrap(), NodeKeypair::generate().unwrap());
+    let (mut a, mut b) = duplex();
+    let tb = thread::spawn(move || signed_handshake(&mut b, &kb, V1, true));
+
+    // узел A подписывает не тот транскрипт
+    let hello = SignedHello::new(&ka, V1).unwrap();
+    send_frame(&mut a, &make_frame(FrameKind::Hello, encode_signed_hello(&hello))).unwrap();
+    let _peer_hello = recv_frame(&mut a).unwrap();
+    let proof = AuthPayload { method: AuthMethod::Signature, data: vec![1; 64] };
+    send_frame(&mut a, &make_frame(FrameKind::Auth, encode_auth(&proof))).unwrap();
+
+    assert_eq!(tb.join().unwrap(), Err(NetError::PeerIdentityMismatch { node: ka.node_id() }));
+    assert_eq!(recv_frame(&mut a).unwrap().header.kind, FrameKind::Auth);
+    expect_unauthorized(&mut a);
+}
+
+#[test]
+fn reflected_hello_and_signature_are_refused() {
+    let keys = NodeKeypair::generate().unwrap();
+    let node = keys.node_id();
+    let (r, _) = against(keys, |b| {
+        // атакующий возвращает узлу его собственные Hello и подпись
+        let hello = recv_frame(b).unwrap();
+        send_frame(b, &hello).unwrap();
+        let next = recv_frame(b).unwrap();
+        if next.header.kind == FrameKind::Auth {
+            send_frame(b, &next).unwrap();
+        }
+    });
+
+    assert_eq!(r, Err(NetError::PeerIdentityMismatch { node }));
+}
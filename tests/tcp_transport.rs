@@ -0,0 +1,100 @@
+use std::thread;
+use std::time::Duration;
+
+use quarxnet::error::NetError;
+use quarxnet::protocol::{decode_hello, encode_hello, recv_frame, send_frame, Transport};
+use quarxnet::transport::{TcpConfig, TcpTransport, TcpTransportListener};
+use quarxnet::frame::{Frame, FrameHeader, FrameKind};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+fn frame(kind: FrameKind, payload: Vec<u8>) -> Frame {
+    Frame {
+        header: FrameHeader::new(kind, payload.len() as u32),
+        payload: payload.into(),
+    }
+}
+
+fn hello(node: u64) -> Frame {
+    frame(
+        FrameKind::Hello,
+        encode_hello(&HelloPayload { node, version: ProtocolVersion { major: 1, minor: 0 } }),
+    )
+}
+
+fn listener(config: TcpConfig) -> TcpTransportListener {
+    TcpTransportListener::bind("127.0.0.1:0", config).unwrap()
+}
+
+#[test]
+fn hello_ping_pong_over_loopback() {
+    let l = listener(TcpConfig::default());
+    let addr = l.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut t, _) = l.accept().unwrap();
+
+        let f = recv_frame(&mut t).unwrap();
+        assert!(matches!(f.header.kind, FrameKind::Hello));
+        assert_eq!(decode_hello(&f.payload).unwrap().node, 1);
+        send_frame(&mut t, &hello(2)).unwrap();
+
+        let f = recv_frame(&mut t).unwrap();
+        assert!(matches!(f.header.kind, FrameKind::Ping));
+        send_frame(&mut t, &frame(FrameKind::Pong, f.payload.to_vec())).unwrap();
+
+        t.shutdown().unwrap();
+    });
+
+    let mut c = TcpTransport::connect(addr, &TcpConfig::default()).unwrap();
+    assert_eq!(c.peer_addr().unwrap(), addr);
+
+    send_frame(&mut c, &hello(1)).unwrap();
+    let f = recv_frame(&mut c).unwrap();
+    assert!(matches!(f.header.kind, FrameKind::Hello));
+    let h = decode_hello(&f.payload).unwrap();
+    assert_eq!((h.node, h.version.major, h.version.minor), (2, 1, 0));
+
+    send_frame(&mut c, &frame(FrameKind::Ping, vec![7, 7, 7])).unwrap();
+    let f = recv_frame(&mut c).unwrap();
+    assert!(matches!(f.header.kind, FrameKind::Pong));
+    assert_eq!(f.payload, vec![7, 7, 7]);
+
+    server.join().unwrap();
+
+    // сервер сделал shutdown — дальше только ConnectionClosed
+    assert_eq!(recv_frame(&mut c).err(), Some(NetError::ConnectionClosed));
+}
+
+#[test]
+fn read_timeout_maps_to_net_error() {
+    let l = listener(TcpConfig::default());
+    let addr = l.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (t, _) = l.accept().unwrap();
+        thread::sleep(Duration::from_millis(300));
+        drop(t);
+    });
+
+    let config = TcpConfig { read_timeout: Some(Duration::from_millis(50)), ..TcpConfig::default() };
+    let mut c = TcpTransport::connect(addr, &config).unwrap();
+    assert_eq!(c.recv_exact(6).err(), Some(NetError::Timeout));
+
+    server.join().unwrap();
+}
+
+#[test]
+fn peer_disappearing_mid_frame_is_connection_closed() {
+    let l = listener(TcpConfig::default());
+    let addr = l.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut t, _) = l.accept().unwrap();
+        // заголовок обещает 100 байт, отправляем только 3
+        t.send(&[7, 0, 0, 0, 0, 100, 1, 2, 3]).unwrap();
+        t.shutdown().unwrap();
+    });
+
+    let mut c = TcpTransport::connect(addr, &TcpConfig::default()).unwrap();
+    assert_eq!(recv_frame(&mut c).err(), Some(NetError::ConnectionClosed));
+
+    server.join().unwrap();
+}
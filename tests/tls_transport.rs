@@ -0,0 +1,168 @@
+#![cfg(feature = "tls")]
+
+use std::path::PathBuf;
+use std::thread;
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+};
+use rustls::RootCertStore;
+
+use quarxnet::error::NetError;
+use quarxnet::frame::FrameKind;
+use quarxnet::protocol::{
+    decode_hello, encode_hello, handshake, make_frame, recv_frame, send_frame,
+};
+use quarxnet::transport::tls::node_name;
+use quarxnet::transport::{TcpConfig, TlsConfig, TlsIdentity, TlsTransport, TlsTransportListener};
+use quarxtor_core::net_core::{HelloPayload, ProtocolVersion};
+
+struct Ca {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+impl Ca {
+    fn new() -> Ca {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        Ca { cert: params.self_signed(&key).unwrap(), key }
+    }
+
+    fn roots(&self) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.cert.der().clone()).unwrap();
+        roots
+    }
+
+    /// (certificate PEM, key PEM) for node `id`.
+    fn issue(&self, id: u64) -> (String, String) {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec![node_name(id)]).unwrap();
+        params.extended_key_usages =
+            vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth];
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+        (cert.pem(), key.serialize_pem())
+    }
+
+    fn config(&self, id: u64) -> TlsConfig {
+        let (cert, key) = self.issue(id);
+        let identity = TlsIdentity::from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        TlsConfig::new(identity, self.roots()).unwrap()
+    }
+}
+
+fn listener(tls: TlsConfig) -> TlsTransportListener {
+    TlsTransportListener::bind("127.0.0.1:0", TcpConfig::default(), tls).unwrap()
+}
+
+fn hello(node: u64) -> HelloPayload {
+    HelloPayload { node, version: ProtocolVersion { major: 1, minor: 0 } }
+}
+
+#[test]
+fn mutual_tls_identities_match_hello() {
+    let ca = Ca::new();
+    let l = listener(ca.config(1));
+    let addr = l.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut t, _) = l.accept().unwrap();
+        let session = handshake(&mut t, &hello(1), false).unwrap();
+        let id = t.peer_identity().unwrap();
+        id.check_node(session.peer_node).unwrap();
+        assert_eq!(id.check_node(3), Err(NetError::PeerIdentityMismatch { node: 3 }));
+
+        let f = recv_frame(&mut t).unwrap();
+        send_frame(&mut t, &make_frame(FrameKind::PushObject, f.payload.to_vec())).unwrap();
+        t.shutdown().unwrap();
+    });
+
+    let mut c = TlsTransport::connect_node(addr, 1, &TcpConfig::default(), &ca.config(2)).unwrap();
+    let session = handshake(&mut c, &hello(2), false).unwrap();
+    assert!(c.peer_identity().unwrap().is_node(session.peer_node));
+
+    send_frame(&mut c, &make_frame(FrameKind::PushObject, vec![9; 70_000])).unwrap();
+    assert_eq!(recv_frame(&mut c).unwrap().payload, vec![9; 70_000]);
+    assert_eq!(recv_frame(&mut c).err(), Some(NetError::ConnectionClosed));
+    server.join().unwrap();
+}
+
+#[test]
+fn config_loads_from_pem_files() {
+    let ca = Ca::new();
+    let dir = std::env::temp_dir().join(format!("quarxnet-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, data: &str| -> PathBuf {
+        let p = dir.join(name);
+        std::fs::write(&p, data).unwrap();
+        p
+    };
+    let (cert, key) = ca.issue(5);
+    let cert = write("node.pem", &cert);
+    let key = write("node.key", &key);
+    let ca_pem = write("ca.pem", &ca.cert.pem());
+
+    let l = listener(TlsConfig::from_pem_files(&cert, &key, &ca_pem).unwrap());
+    let addr = l.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut t, _) = l.accept().unwrap();
+        let f = recv_frame(&mut t).unwrap();
+        assert_eq!(decode_hello(&f.payload).unwrap().node, 6);
+        t.peer_identity().unwrap().check_node(6).unwrap();
+    });
+
+    let mut c = TlsTransport::connect_node(addr, 5, &TcpConfig::default(), &ca.config(6)).unwrap();
+    send_frame(&mut c, &make_frame(FrameKind::Hello, encode_hello(&hello(6)))).unwrap();
+    server.join().unwrap();
+
+    let missing = dir.join("missing.pem");
+    assert!(matches!(TlsConfig::from_pem_files(&missing, &key, &ca_pem), Err(NetError::Tls(_))));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn client_from_foreign_ca_is_rejected() {
+    let ca = Ca::new();
+    let l = listener(ca.config(1));
+    let addr = l.local_addr().unwrap();
+    let server = thread::spawn(move || l.accept().err());
+
+    // клиент доверяет нашему CA, но его сертификат выпущен чужим
+    let rogue = Ca::new();
+    let (cert, key) = rogue.issue(2);
+    let identity = TlsIdentity::from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+    let tls = TlsConfig::new(identity, ca.roots()).unwrap();
+
+    let client = TlsTransport::connect_node(addr, 1, &TcpConfig::default(), &tls)
+        .and_then(|mut c| recv_frame(&mut c).map(|_| ()));
+    assert!(client.is_err());
+    assert!(matches!(server.join().unwrap(), Some(NetError::Tls(_))));
+}
+
+#[test]
+fn server_with_wrong_node_name_is_rejected() {
+    let ca = Ca::new();
+    let l = listener(ca.config(1));
+    let addr = l.local_addr().unwrap();
+    let server = thread::spawn(move || l.accept().err());
+
+    let res = TlsTransport::connect_node(addr, 7, &TcpConfig::default(), &ca.config(2));
+    assert!(matches!(res.err(), Some(NetError::Tls(_))));
+    assert!(server.join().unwrap().is_some());
+}
+
+#[test]
+fn optional_client_auth_accepts_but_reports_identity() {
+    let ca = Ca::new();
+    let l = listener(ca.config(1).with_optional_client_auth().unwrap());
+    let addr = l.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (t, _) = l.accept().unwrap();
+        t.peer_identity().map(|id| id.is_node(2))
+    });
+
+    let _c = TlsTransport::connect_node(addr, 1, &TcpConfig::default(), &ca.config(2)).unwrap();
+    assert_eq!(server.join().unwrap(), Some(true));
+}
@@ -0,0 +1,93 @@
+use std::thread;
+
+use quarxnet::error::NetError;
+use quarxnet::frame::{Frame, FrameHeader, FrameKind};
+use quarxnet::protocol::{
+    encode_frame_into, encode_push_object_into, recv_frame, send_frame_vectored, send_frame_with,
+    FrameOptions, Transport,
+};
+use quarxnet::transport::memory::duplex;
+use quarxnet::transport::{TcpConfig, TcpTransport, TcpTransportListener};
+use quarxtor_core::net_core::PushObjectPayload;
+
+fn opts(checksum: bool) -> FrameOptions {
+    FrameOptions { checksum, ..FrameOptions::default() }
+}
+
+#[test]
+fn encode_into_matches_what_send_writes() {
+    for checksum in [false, true] {
+        let frame = Frame::new(FrameKind::PushObject, vec![9; 300]).with_request_id(77);
+        let (mut a, mut b) = duplex();
+        send_frame_with(&mut a, &frame, &opts(checksum)).unwrap();
+
+        let mut out = Vec::new();
+        encode_frame_into(&frame, &opts(checksum), &mut out).unwrap();
+        assert_eq!(b.recv_exact(out.len()).unwrap(), out);
+    }
+}
+
+#[test]
+fn encode_into_appends_to_reused_buffer() {
+    let a = Frame::new(FrameKind::Ping, vec![1]);
+    let b = Frame::new(FrameKind::Pong, vec![2, 3]);
+
+    let mut buf = Vec::new();
+    encode_frame_into(&a, &FrameOptions::default(), &mut buf).unwrap();
+    let first = buf.len();
+    encode_frame_into(&b, &FrameOptions::default(), &mut buf).unwrap();
+    assert_eq!(buf.len(), first + 6 + 2);
+
+    let mut payload = vec![0xFF];
+    encode_push_object_into(&PushObjectPayload { raw: vec![4, 5] }, &mut payload);
+    assert_eq!(payload, vec![0xFF, 4, 5]);
+}
+
+#[test]
+fn vectored_send_roundtrips_with_extensions() {
+    let raw = vec![0x5A; 4096];
+    for checksum in [false, true] {
+        let (mut a, mut b) = duplex();
+        let mut header = FrameHeader::new(FrameKind::PushObject, 0);
+        header.request_id = Some(3);
+        header.flags = 0x40;
+        send_frame_vectored(&mut a, &header, &raw, &opts(checksum)).unwrap();
+
+        let f = recv_frame(&mut b).unwrap();
+        assert_eq!(f.header, FrameHeader { length: 4096, ..header });
+        assert_eq!(f.payload, raw);
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn payload_over_4_gib_is_refused() {
+    // страницы нулевого буфера не трогаются: ошибка раньше чтения payload
+    let huge = vec![0u8; u32::MAX as usize + 1];
+    let too_large = NetError::FrameTooLarge { kind: 6, length: u32::MAX, max: u32::MAX };
+    let header = FrameHeader::new(FrameKind::PushObject, 0);
+
+    let (mut a, mut b) = duplex();
+    assert_eq!(send_frame_vectored(&mut a, &header, &huge, &opts(false)), Err(too_large));
+    drop(a);
+    assert!(b.recv_exact(1).is_err());
+}
+
+#[test]
+fn large_vectored_frame_over_tcp() {
+    let l = TcpTransportListener::bind("127.0.0.1:0", TcpConfig::default()).unwrap();
+    let addr = l.local_addr().unwrap();
+    let raw: Vec<u8> = (0..1024 * 1024).map(|i| i as u8).collect();
+
+    let expected = raw.clone();
+    let server = thread::spawn(move || {
+        let (mut t, _) = l.accept().unwrap();
+        let f = recv_frame(&mut t).unwrap();
+        assert_eq!(f.payload, expected);
+    });
+
+    let mut t = TcpTransport::connect(addr, &TcpConfig::default()).unwrap();
+    let header = FrameHeader::new(FrameKind::PushObject, 0);
+    send_frame_vectored(&mut t, &header, &raw, &opts(true)).unwrap();
+    server.join().unwrap();
+}